@@ -1,19 +1,32 @@
 use async_trait::async_trait;
 
-use crate::Player;
+use crate::{EloError, Player};
 
 use std::collections::HashMap;
 
 use std::sync::RwLock;
 
 #[async_trait]
-pub trait AsyncEloStorage {
+pub trait AsyncEloStorage: Sync {
     /// Add a new player
     async fn add_player(&self, player: Player);
     /// Update the rating and number of games played for a player
     async fn update_player(&self, player: &Player);
     /// Get the player
     async fn get(&self, name: &str) -> Option<Player>;
+    /// Get every player, in no particular order
+    async fn all_players(&self) -> Vec<Player>;
+    /// Get up to `limit` players sorted by name, skipping the first `offset`, like
+    /// `LIMIT`/`OFFSET` in SQL
+    async fn players_paged(&self, offset: usize, limit: usize) -> Vec<Player> {
+        let mut players = self.all_players().await;
+        players.sort_by(|a, b| a.name().cmp(b.name()));
+        players.into_iter().skip(offset).take(limit).collect()
+    }
+    /// Check that the storage is reachable, like pinging a database
+    async fn healthy(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -34,11 +47,7 @@ impl<S: AsyncEloStorage> AsyncElo<S> {
     #[allow(dead_code)]
     pub async fn add_player<TS: ToString>(&self, name: TS) {
         self.players
-            .add_player(Player {
-                name: name.to_string(),
-                rating: self.starting_elo,
-                number_of_games: 0,
-            })
+            .add_player(Player::new(name.to_string(), self.starting_elo, 0))
             .await;
     }
 
@@ -57,12 +66,9 @@ impl<S: AsyncEloStorage> AsyncElo<S> {
         player1: &str,
         player2: &str,
         is_draw: bool,
-    ) -> Result<(), String> {
+    ) -> Result<(), EloError> {
         if player1 == player2 {
-            return Err(format!(
-                "{} can't play against themselves (you friendless loser)",
-                player1
-            ));
+            return Err(EloError::SelfPlay(player1.to_string()));
         }
 
         self.try_add(player1).await;
@@ -90,24 +96,76 @@ impl<S: AsyncEloStorage> AsyncElo<S> {
         self.players.get(name).await
     }
 
+    #[allow(dead_code)]
+    pub async fn players(&self) -> Vec<Player> {
+        self.players.all_players().await
+    }
+
+    /// A page of at most `limit` players in name order, starting after the first `offset`.
+    #[allow(dead_code)]
+    pub async fn players_paged(&self, offset: usize, limit: usize) -> Vec<Player> {
+        self.players.players_paged(offset, limit).await
+    }
+
+    #[allow(dead_code)]
+    pub async fn healthy(&self) -> bool {
+        self.players.healthy().await
+    }
+
+    #[allow(dead_code)]
+    pub fn storage(&self) -> &S {
+        &self.players
+    }
+
+    #[allow(dead_code)]
+    pub fn storage_mut(&mut self) -> &mut S {
+        &mut self.players
+    }
+
+    /// Move every player over to a new storage, like when migrating from memory to a database.
+    #[allow(dead_code)]
+    pub async fn replace_storage<T: AsyncEloStorage>(self, new: T) -> AsyncElo<T> {
+        for player in self.players.all_players().await {
+            new.add_player(player).await;
+        }
+
+        AsyncElo {
+            players: new,
+            starting_elo: self.starting_elo,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn into_storage(self) -> S {
         self.players
     }
 
     #[allow(dead_code)]
-    pub async fn set_rating(&self, player: &str, rating: usize) {
-        let mut player = self.players.get(player).await.unwrap();
+    pub async fn set_rating(&self, player: &str, rating: usize) -> Result<(), EloError> {
+        let mut player = self
+            .players
+            .get(player)
+            .await
+            .ok_or_else(|| EloError::PlayerNotFound(player.to_string()))?;
         player.rating = rating;
         self.players.update_player(&player).await;
+        Ok(())
     }
 
     #[allow(dead_code)]
-    pub async fn set_number_of_games(&self, player: &str, number_of_games: usize) {
-        let mut player = self.players.get(player).await.unwrap();
+    pub async fn set_number_of_games(
+        &self,
+        player: &str,
+        number_of_games: usize,
+    ) -> Result<(), EloError> {
+        let mut player = self
+            .players
+            .get(player)
+            .await
+            .ok_or_else(|| EloError::PlayerNotFound(player.to_string()))?;
         player.number_of_games = number_of_games;
         self.players.update_player(&player).await;
-
+        Ok(())
     }
 }
 
@@ -143,6 +201,22 @@ impl AsyncEloStorage for InMemoryStorage {
     async fn get(&self, name: &str) -> Option<Player> {
         self.players.read().unwrap().get(name).cloned()
     }
+
+    async fn all_players(&self) -> Vec<Player> {
+        self.players.read().unwrap().values().cloned().collect()
+    }
+
+    async fn players_paged(&self, offset: usize, limit: usize) -> Vec<Player> {
+        let players = self.players.read().unwrap();
+        let mut names = players.keys().collect::<Vec<_>>();
+        names.sort();
+        names
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|name| players[name].clone())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -185,6 +259,77 @@ mod tests {
         assert_eq!(elo.get_player("b").await.unwrap().rating(), 1000);
     }
 
+    #[tokio::test]
+    async fn set_missing_player() {
+        let elo = AsyncElo::new(InMemoryStorage::new());
+
+        assert_eq!(
+            elo.set_rating("a", 1200).await,
+            Err(EloError::PlayerNotFound("a".to_string()))
+        );
+        assert_eq!(
+            elo.set_number_of_games("a", 3).await,
+            Err(EloError::PlayerNotFound("a".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn storage() {
+        let mut elo = AsyncElo::new(InMemoryStorage::new());
+        elo.add_game("a", "b", false).await.unwrap();
+        assert_eq!(elo.storage().players.read().unwrap().len(), 2);
+
+        elo.storage_mut().players.write().unwrap().remove("b");
+        assert!(elo.get_player("b").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn players() {
+        let elo = AsyncElo::new(InMemoryStorage::new());
+        elo.add_player("a").await;
+        elo.add_player("b").await;
+        elo.add_player("c").await;
+
+        let mut players = elo.players().await;
+        players.sort();
+        let names = players.iter().map(|p| p.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn players_paged() {
+        let elo = AsyncElo::new(InMemoryStorage::new());
+        for name in ["e", "c", "a", "d", "b"] {
+            elo.add_player(name).await;
+        }
+
+        let mut names = Vec::new();
+        for (offset, len) in [(0, 2), (2, 2), (4, 1)] {
+            let page = elo.players_paged(offset, 2).await;
+            assert_eq!(page.len(), len);
+            names.extend(page.into_iter().map(|p| p.name().to_string()));
+        }
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+        assert!(elo.players_paged(5, 2).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn healthy() {
+        let elo = AsyncElo::new(InMemoryStorage::new());
+        assert!(elo.healthy().await);
+    }
+
+    #[tokio::test]
+    async fn replace_storage() {
+        let elo = AsyncElo::new(InMemoryStorage::new());
+        elo.add_game("a", "b", false).await.unwrap();
+
+        let elo = elo.replace_storage(InMemoryStorage::new()).await;
+        assert_eq!(elo.players().await.len(), 2);
+        assert_eq!(elo.get_player("a").await.unwrap().rating(), 1016);
+        assert_eq!(elo.get_player("b").await.unwrap().number_of_games(), 1);
+    }
+
     #[tokio::test]
     async fn ordering() {
         let elo = AsyncElo::new(InMemoryStorage::new());
@@ -198,15 +343,15 @@ mod tests {
         elo.add_game("a", "c", false).await.unwrap();
 
         // force b rating, to see ordering with comparison of c
-        elo.set_rating("b", 985).await;
+        elo.set_rating("b", 985).await.unwrap();
 
         // add player d, see check that name is ordered lexicographically
-        elo.set_rating("d", 985).await;
-        elo.set_number_of_games("d", 2).await;
+        elo.set_rating("d", 985).await.unwrap();
+        elo.set_number_of_games("d", 2).await.unwrap();
 
         let hm = elo.into_storage();
         let players = hm.players.read().unwrap();
-        let mut players = players.iter().map(|(_, v)| v).collect::<Vec<_>>();
+        let mut players = players.values().collect::<Vec<_>>();
         players.sort();
         assert_eq!(players[0].name(), "a");
         assert_eq!(players[1].name(), "b");
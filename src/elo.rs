@@ -1,8 +1,11 @@
-use crate::Player;
+use crate::{EloError, GameResult, Model, Player, TieBreak};
 
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     ops::{Index, IndexMut},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 pub trait EloStorage {
@@ -10,157 +13,5102 @@ pub trait EloStorage {
     fn update_player(&mut self, player: &Player);
     fn get(&self, name: &str) -> Option<&Player>;
     fn get_mut(&mut self, name: &str) -> Option<&mut Player>;
+    fn all_players(&self) -> Vec<&Player>;
+    fn remove_player(&mut self, name: &str) -> Option<Player>;
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The current time in seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// How many games `Elo::games_to_overtake` looks ahead
+const MAX_OVERTAKE_GAMES: usize = 1000;
+
+/// The widest name `Elo::to_ascii_table` shows before truncating
+const ASCII_NAME_WIDTH: usize = 16;
+
+/// How far from scoring half the points a player can be in `Elo::has_plateaued`
+const PLATEAU_SCORE_MARGIN: f64 = 0.15;
+
+/// The rating penalty of a player without games in `Elo::confident_leaderboard`
+const CONFIDENCE_PENALTY: f64 = 200.0;
+
+/// How much the rating deviation of an inactive player grows per rating period, as in Glicko
+const DEVIATION_GROWTH: f64 = 35.0;
+
+/// The rating deviation of an unrated player in Glicko, which decay never goes past
+const MAX_DEVIATION: f64 = 350.0;
+
+/// Settings for an `Elo`, set up through `EloBuilder`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EloConfig {
+    starting_elo: usize,
+    k_factor: f64,
+    history: bool,
+    h2h_window: usize,
+    case_insensitive: bool,
+    recent_results_window: usize,
+    expected_clamp: Option<(f64, f64)>,
+    max_players: Option<usize>,
+    precision_multiplier: usize,
+    momentum: f64,
+    inflation_sink: f64,
+    provisional_games: usize,
+    recency_decay: f64,
+    model: Model,
+    daily_rated_cap: Option<usize>,
+    milestones: Vec<usize>,
+    period_decay: f64,
+    rating_period: u64,
+    provisional_k_factor: Option<f64>,
+    provisional_cap: Option<u32>,
+    strict: bool,
+    rating_ceiling: Option<usize>,
+    ema_alpha: f64,
+    allow_draws: bool,
+}
+
+impl Default for EloConfig {
+    fn default() -> Self {
+        EloConfig {
+            starting_elo: 1000,
+            k_factor: crate::K_FACTOR,
+            history: false,
+            h2h_window: 25,
+            case_insensitive: false,
+            recent_results_window: 0,
+            expected_clamp: None,
+            max_players: None,
+            precision_multiplier: 1,
+            momentum: 0.0,
+            inflation_sink: 0.0,
+            provisional_games: 0,
+            recency_decay: 1.0,
+            model: Model::default(),
+            daily_rated_cap: None,
+            milestones: Vec::new(),
+            period_decay: 0.05,
+            rating_period: 30 * SECONDS_PER_DAY,
+            provisional_k_factor: None,
+            provisional_cap: None,
+            strict: false,
+            rating_ceiling: None,
+            ema_alpha: 0.3,
+            allow_draws: true,
+        }
+    }
+}
+
+impl EloConfig {
+    pub fn starting_elo(&self) -> usize {
+        self.starting_elo
+    }
+
+    pub fn k_factor(&self) -> f64 {
+        self.k_factor
+    }
+
+    pub fn history(&self) -> bool {
+        self.history
+    }
+
+    pub fn h2h_window(&self) -> usize {
+        self.h2h_window
+    }
+
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    pub fn recent_results_window(&self) -> usize {
+        self.recent_results_window
+    }
+
+    pub fn expected_clamp(&self) -> Option<(f64, f64)> {
+        self.expected_clamp
+    }
+
+    pub fn max_players(&self) -> Option<usize> {
+        self.max_players
+    }
+
+    pub fn precision_multiplier(&self) -> usize {
+        self.precision_multiplier
+    }
+
+    pub fn momentum(&self) -> f64 {
+        self.momentum
+    }
+
+    pub fn inflation_sink(&self) -> f64 {
+        self.inflation_sink
+    }
+
+    pub fn provisional_games(&self) -> usize {
+        self.provisional_games
+    }
+
+    pub fn recency_decay(&self) -> f64 {
+        self.recency_decay
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn daily_rated_cap(&self) -> Option<usize> {
+        self.daily_rated_cap
+    }
+
+    pub fn milestones(&self) -> &[usize] {
+        &self.milestones
+    }
+
+    pub fn period_decay(&self) -> f64 {
+        self.period_decay
+    }
+
+    pub fn rating_period(&self) -> u64 {
+        self.rating_period
+    }
+
+    pub fn provisional_k_factor(&self) -> Option<f64> {
+        self.provisional_k_factor
+    }
+
+    pub fn provisional_cap(&self) -> Option<u32> {
+        self.provisional_cap
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn rating_ceiling(&self) -> Option<usize> {
+        self.rating_ceiling
+    }
+
+    pub fn ema_alpha(&self) -> f64 {
+        self.ema_alpha
+    }
+
+    pub fn allow_draws(&self) -> bool {
+        self.allow_draws
+    }
+}
+
+pub struct EloBuilder<S: EloStorage> {
+    players: S,
+    config: EloConfig,
+}
+
+impl<S: EloStorage> EloBuilder<S> {
+    pub fn new(players: S) -> Self {
+        EloBuilder {
+            players,
+            config: EloConfig::default(),
+        }
+    }
+
+    /// The rating given to new players.
+    pub fn starting_elo(mut self, starting_elo: usize) -> Self {
+        self.config.starting_elo = starting_elo;
+        self
+    }
+
+    /// The maximum rating change from a single game.
+    pub fn k_factor(mut self, k_factor: f64) -> Self {
+        self.config.k_factor = k_factor;
+        self
+    }
+
+    /// Keep a log of every game played.
+    pub fn history(mut self, history: bool) -> Self {
+        self.config.history = history;
+        self
+    }
+
+    /// Players within this many points of each other are ordered by head-to-head record in
+    /// `Elo::leaderboard_h2h`.
+    pub fn h2h_window(mut self, h2h_window: usize) -> Self {
+        self.config.h2h_window = h2h_window;
+        self
+    }
+
+    /// Treat names differing only in case as the same player.
+    /// Players keep the name they were first added with as their display name.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.config.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Keep the results of the last `window` games of each player.
+    pub fn recent_results_window(mut self, window: usize) -> Self {
+        self.config.recent_results_window = window;
+        self
+    }
+
+    /// Clamp the expected score into `(min, max)` when updating ratings, which limits how much an
+    /// upset between very differently rated players can move the ratings.
+    ///
+    /// # Panics
+    /// If the bounds are not `0 <= min <= max <= 1`.
+    pub fn expected_clamp(mut self, min: f64, max: f64) -> Self {
+        assert!(
+            0.0 <= min && min <= max && max <= 1.0,
+            "expected score bounds must satisfy 0 <= min <= max <= 1, got ({}, {})",
+            min,
+            max
+        );
+        self.config.expected_clamp = Some((min, max));
+        self
+    }
+
+    /// The maximum number of players the pool may hold.
+    pub fn max_players(mut self, max_players: usize) -> Self {
+        self.config.max_players = Some(max_players);
+        self
+    }
+
+    /// Store ratings as integer multiples of `1 / multiplier` points, so a multiplier of 10 keeps
+    /// ratings to the tenth of a point. `Player::rating` is then in these finer units, while
+    /// `Elo::rating_display` gives the rating in points. Rating changes, like the deltas of a
+    /// `GameRecord`, `Elo::match_log` and `diff`, are always in points.
+    pub fn precision_multiplier(mut self, multiplier: usize) -> Self {
+        self.config.precision_multiplier = multiplier.max(1);
+        self
+    }
+
+    /// Damp every rating change to `(1 - momentum)` of its size, which keeps players who keep
+    /// trading wins closer to their equilibrium. Should be in `[0, 1)`.
+    pub fn momentum(mut self, momentum: f64) -> Self {
+        self.config.momentum = momentum;
+        self
+    }
+
+    /// After every game, pull the new ratings this fraction of the way back to the starting elo,
+    /// which keeps the pool mean from drifting as players come and go. Should be small, like 0.01.
+    pub fn inflation_sink(mut self, sink: f64) -> Self {
+        self.config.inflation_sink = sink;
+        self
+    }
+
+    /// The number of games a player is provisional for, before becoming established.
+    pub fn provisional_games(mut self, games: usize) -> Self {
+        self.config.provisional_games = games;
+        self
+    }
+
+    /// The K-factor for provisional players, usually higher so new players find their rating
+    /// quickly.
+    pub fn provisional_k_factor(mut self, k_factor: f64) -> Self {
+        self.config.provisional_k_factor = Some(k_factor);
+        self
+    }
+
+    /// The most points a provisional player can gain or lose in a single game.
+    pub fn provisional_cap(mut self, cap: u32) -> Self {
+        self.config.provisional_cap = Some(cap);
+        self
+    }
+
+    /// How much less each older game counts in `Elo::replay_weighted`, in `(0, 1]`.
+    pub fn recency_decay(mut self, decay: f64) -> Self {
+        self.config.recency_decay = decay;
+        self
+    }
+
+    /// How expected scores are computed from ratings.
+    pub fn model(mut self, model: Model) -> Self {
+        self.config.model = model;
+        self
+    }
+
+    /// Only the first `cap` games of a player each (UTC) day change their rating. Later games are
+    /// still counted and recorded.
+    pub fn daily_rated_cap(mut self, cap: usize) -> Self {
+        self.config.daily_rated_cap = Some(cap);
+        self
+    }
+
+    /// Rating thresholds to celebrate. A player reaching one for the first time gets it added to
+    /// their `Player::milestones`.
+    pub fn milestones(mut self, thresholds: Vec<usize>) -> Self {
+        self.config.milestones = thresholds;
+        self
+    }
+
+    /// The fraction of the distance to the pool mean an inactive player loses per rating period
+    /// in `Elo::apply_period_decay`.
+    pub fn period_decay(mut self, decay: f64) -> Self {
+        self.config.period_decay = decay;
+        self
+    }
+
+    /// The length of a rating period in seconds, 30 days by default.
+    pub fn rating_period(mut self, secs: u64) -> Self {
+        self.config.rating_period = secs;
+        self
+    }
+
+    /// In strict mode games with players that were not added first are rejected, rather than
+    /// adding the players. This holds for every kind of game, including team games and byes.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// The highest rating a game can leave a player at, for capped tier leagues.
+    pub fn rating_ceiling(mut self, ceiling: usize) -> Self {
+        self.config.rating_ceiling = Some(ceiling);
+        self
+    }
+
+    /// How much of the new rating goes into `Player::ema_rating` after each game, in `(0, 1]`.
+    ///
+    /// # Panics
+    /// If `alpha` is not in `(0, 1]`.
+    pub fn ema_alpha(mut self, alpha: f64) -> Self {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "ema alpha must be in (0, 1], got {}",
+            alpha
+        );
+        self.config.ema_alpha = alpha;
+        self
+    }
+
+    /// For games that can't end in a draw: when false, entering a draw is an error.
+    pub fn allow_draws(mut self, allow_draws: bool) -> Self {
+        self.config.allow_draws = allow_draws;
+        self
+    }
+
+    pub fn build(self) -> Elo<S> {
+        Elo {
+            players: self.players,
+            config: self.config,
+            history: Vec::new(),
+            on_established: None,
+            locked: false,
+            audit_log: Vec::new(),
+        }
+    }
+}
+
+/// A game as recorded in the history of an `Elo`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GameRecord {
+    player1: String,
+    player2: String,
+    is_draw: bool,
+    player1_rating_before: usize,
+    player2_rating_before: usize,
+    player1_rating_after: usize,
+    player2_rating_after: usize,
+    player1_expected: f64,
+    player1_delta: i64,
+    player2_delta: i64,
+    timestamp: u64,
+}
+
+impl GameRecord {
+    pub fn player1(&self) -> &str {
+        &self.player1
+    }
+
+    pub fn player2(&self) -> &str {
+        &self.player2
+    }
+
+    /// If false, the game was won by player1.
+    pub fn is_draw(&self) -> bool {
+        self.is_draw
+    }
+
+    pub fn player1_rating_before(&self) -> usize {
+        self.player1_rating_before
+    }
+
+    pub fn player2_rating_before(&self) -> usize {
+        self.player2_rating_before
+    }
+
+    pub fn player1_rating_after(&self) -> usize {
+        self.player1_rating_after
+    }
+
+    pub fn player2_rating_after(&self) -> usize {
+        self.player2_rating_after
+    }
+
+    /// The expected score of player1 before the game
+    pub fn player1_expected(&self) -> f64 {
+        self.player1_expected
+    }
+
+    /// The rating change of player1 caused by the game, in points
+    pub fn player1_delta(&self) -> i64 {
+        self.player1_delta
+    }
+
+    pub fn player2_delta(&self) -> i64 {
+        self.player2_delta
+    }
+
+    /// When the game was played, in seconds since the unix epoch
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+/// A won game, with the ratings and expectations from before it was played.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UpsetRecord {
+    winner: String,
+    loser: String,
+    winner_rating: usize,
+    loser_rating: usize,
+    winner_expected: f64,
+}
+
+impl UpsetRecord {
+    pub fn winner(&self) -> &str {
+        &self.winner
+    }
+
+    pub fn loser(&self) -> &str {
+        &self.loser
+    }
+
+    pub fn winner_rating(&self) -> usize {
+        self.winner_rating
+    }
+
+    pub fn loser_rating(&self) -> usize {
+        self.loser_rating
+    }
+
+    pub fn winner_expected(&self) -> f64 {
+        self.winner_expected
+    }
+}
+
+/// A rating change made outside of games, like a bonus, kept in the audit log.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AuditEntry {
+    player: String,
+    points: usize,
+    reason: String,
+}
+
+impl AuditEntry {
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    /// The points added, in rating points rather than stored units
+    pub fn points(&self) -> usize {
+        self.points
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+struct EstablishedHook(Box<dyn FnMut(&Player) + Send>);
+
+impl std::fmt::Debug for EstablishedHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EstablishedHook")
+    }
 }
 
 #[derive(Debug)]
 pub struct Elo<S: EloStorage> {
     players: S,
-    starting_elo: usize,
+    config: EloConfig,
+    history: Vec<GameRecord>,
+    on_established: Option<EstablishedHook>,
+    locked: bool,
+    audit_log: Vec<AuditEntry>,
 }
 
 impl<S: EloStorage> Elo<S> {
     #[allow(dead_code)]
     pub fn new(players: S) -> Elo<S> {
-        Elo {
-            players,
-            starting_elo: 1000,
+        EloBuilder::new(players).build()
+    }
+
+    #[allow(dead_code)]
+    pub fn config(&self) -> &EloConfig {
+        &self.config
+    }
+
+    /// The storage key for a name
+    fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.config.case_insensitive {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+
+    /// Add a player with the starting elo, replacing any existing player with the same name.
+    #[allow(dead_code)]
+    pub fn add_player<TS: ToString>(&mut self, name: TS) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let display_name = name.to_string();
+        let key = self.key(&display_name).into_owned();
+
+        if let Some(max_players) = self.config.max_players {
+            if self.players.get(&key).is_none() && self.players.all_players().len() >= max_players {
+                return Err(EloError::PoolFull(max_players));
+            }
+        }
+
+        let mut player = Player::new(key, self.starting_rating(), 0);
+        player.display_name = display_name;
+        self.players.add_player(player);
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn try_add(&mut self, name: &str) -> Result<(), EloError> {
+        if self.get_player(name).is_none() {
+            self.add_player(name)?;
+        }
+        Ok(())
+    }
+
+    /// If is_draw is true, the game is a draw.
+    /// If is_draw is false, the game is won by the first player.
+    #[allow(dead_code)]
+    pub fn add_game(
+        &mut self,
+        player1: &str,
+        player2: &str,
+        is_draw: bool,
+    ) -> Result<(), EloError> {
+        self.add_game_at(player1, player2, is_draw, now())
+    }
+
+    fn check_unlocked(&self) -> Result<(), EloError> {
+        if self.locked {
+            return Err(EloError::PoolLocked);
+        }
+        Ok(())
+    }
+
+    fn check_draw(&self, is_draw: bool) -> Result<(), EloError> {
+        if is_draw && !self.config.allow_draws {
+            return Err(EloError::DrawsNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Check that the players of a game are different, and in strict mode that they exist
+    fn check_players(&self, player1: &str, player2: &str) -> Result<(), EloError> {
+        if self.key(player1) == self.key(player2) {
+            return Err(EloError::SelfPlay(player1.to_string()));
+        }
+        self.check_known(&[player1, player2])
+    }
+
+    /// In strict mode, check that all the players exist
+    fn check_known(&self, players: &[&str]) -> Result<(), EloError> {
+        if self.config.strict {
+            for player in players {
+                if self.get_player(player).is_none() {
+                    return Err(EloError::PlayerNotFound(player.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a batch of games (player1, player2, is_draw) before adding them, returning the index
+    /// and problem of every game that would be rejected: draws when they are not allowed,
+    /// self-play, and in strict mode unknown players. Nothing is changed.
+    #[allow(dead_code)]
+    pub fn validate_games(&self, games: &[(String, String, bool)]) -> Vec<(usize, EloError)> {
+        games
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (player1, player2, is_draw))| {
+                self.check_draw(*is_draw)
+                    .and_then(|_| self.check_players(player1, player2))
+                    .err()
+                    .map(|e| (i, e))
+            })
+            .collect()
+    }
+
+    /// Like `add_game`, but returns copies of both players after the game.
+    #[allow(dead_code)]
+    pub fn add_game_returning(
+        &mut self,
+        player1: &str,
+        player2: &str,
+        is_draw: bool,
+    ) -> Result<(Player, Player), EloError> {
+        self.add_game(player1, player2, is_draw)?;
+        Ok((self[player1].clone(), self[player2].clone()))
+    }
+
+    /// Like `add_game`, but played at `timestamp` (seconds since the unix epoch) rather than now.
+    #[allow(dead_code)]
+    pub fn add_game_at(
+        &mut self,
+        player1: &str,
+        player2: &str,
+        is_draw: bool,
+        timestamp: u64,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        self.check_draw(is_draw)?;
+        self.check_players(player1, player2)?;
+
+        if let Some(max_players) = self.config.max_players {
+            let new_players = [player1, player2]
+                .iter()
+                .filter(|name| self.get_player(name).is_none())
+                .count();
+            if self.players.all_players().len() + new_players > max_players {
+                return Err(EloError::PoolFull(max_players));
+            }
+        }
+
+        self.try_add(player1)?;
+        self.try_add(player2)?;
+
+        let player1 = &*self.key(player1).into_owned();
+        let player2 = &*self.key(player2).into_owned();
+
+        let (rating1, rating2) = (self[player1].rating, self[player2].rating);
+        let (wr, lr) = self.new_ratings(&self[player1], &self[player2], is_draw);
+
+        let (result1, result2) = if is_draw {
+            (GameResult::Draw, GameResult::Draw)
+        } else {
+            (GameResult::Win, GameResult::Loss)
+        };
+
+        let wr = self.finish_rated_game(player1, wr, result1, timestamp);
+        let lr = self.finish_rated_game(player2, lr, result2, timestamp);
+
+        if self.config.history {
+            self.history.push(GameRecord {
+                player1: player1.to_string(),
+                player2: player2.to_string(),
+                is_draw,
+                player1_rating_before: rating1,
+                player2_rating_before: rating2,
+                player1_rating_after: wr,
+                player2_rating_after: lr,
+                player1_expected: self.expected(rating1, rating2),
+                player1_delta: self.points_delta(rating1, wr),
+                player2_delta: self.points_delta(rating2, lr),
+                timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Finish a game of the player played at `timestamp`, returning their rating after it.
+    /// Games over the daily cap are counted, but leave the rating alone.
+    fn finish_rated_game(
+        &mut self,
+        key: &str,
+        new_rating: usize,
+        result: GameResult,
+        timestamp: u64,
+    ) -> usize {
+        let player = self.players.get(key).unwrap();
+        let day = timestamp / SECONDS_PER_DAY;
+        let games_today = if player.last_played.map(|t| t / SECONDS_PER_DAY) == Some(day) {
+            player.games_today
+        } else {
+            0
+        };
+        let new_rating = match self.config.daily_rated_cap {
+            Some(cap) if games_today >= cap => player.rating,
+            _ => new_rating,
+        };
+
+        self.finish_game(key, new_rating, result);
+        self.mark_played(key, timestamp);
+        new_rating
+    }
+
+    /// Count a game played at `timestamp` towards the games of the day of the player
+    fn mark_played(&mut self, key: &str, timestamp: u64) {
+        let day = timestamp / SECONDS_PER_DAY;
+        let player = self.players.get_mut(key).unwrap();
+        if player.last_played.map(|t| t / SECONDS_PER_DAY) == Some(day) {
+            player.games_today += 1;
+        } else {
+            player.games_today = 1;
+        }
+        player.last_played = Some(timestamp);
+    }
+
+    /// Record a game between two teams, rated as a game between their average ratings. Every
+    /// player of a team gets the rating change of the team.
+    /// If is_draw is false, the game is won by team1.
+    #[allow(dead_code)]
+    pub fn add_team_game(
+        &mut self,
+        team1: &[&str],
+        team2: &[&str],
+        is_draw: bool,
+    ) -> Result<(), EloError> {
+        let (score1, score2) = if is_draw { (1, 1) } else { (1, 0) };
+        self.add_team_game_score(team1, team2, score1, score2)
+    }
+
+    /// Record a team game with a scoreline, where team1 is counted as scoring
+    /// `score1 / (score1 + score2)` of the game, so a 3-1 win moves ratings less than 4-0.
+    #[allow(dead_code)]
+    pub fn add_team_game_score(
+        &mut self,
+        team1: &[&str],
+        team2: &[&str],
+        score1: u32,
+        score2: u32,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        self.check_draw(score1 == score2)?;
+        if team1.is_empty() || team2.is_empty() {
+            return Err(EloError::EmptyTeam);
+        }
+
+        let keys = |team: &[&str]| -> Vec<String> {
+            team.iter()
+                .map(|name| self.key(name).into_owned())
+                .collect()
+        };
+        let (keys1, keys2) = (keys(team1), keys(team2));
+        if let Some(name) = keys1.iter().find(|name| keys2.contains(name)) {
+            return Err(EloError::SelfPlay(name.clone()));
+        }
+        for keys in [&keys1, &keys2] {
+            for (i, name) in keys.iter().enumerate() {
+                if keys[..i].contains(name) {
+                    return Err(EloError::InvalidPlayer(format!(
+                        "{} is in the team twice",
+                        name
+                    )));
+                }
+            }
+        }
+
+        self.check_known(team1)?;
+        self.check_known(team2)?;
+
+        if let Some(max_players) = self.config.max_players {
+            let new_players = team1
+                .iter()
+                .chain(team2)
+                .filter(|name| self.get_player(name).is_none())
+                .count();
+            if self.players.all_players().len() + new_players > max_players {
+                return Err(EloError::PoolFull(max_players));
+            }
+        }
+
+        for name in team1.iter().chain(team2) {
+            self.try_add(name)?;
+        }
+
+        let average = |keys: &[String]| {
+            keys.iter()
+                .map(|key| self.players.get(key).unwrap().rating)
+                .sum::<usize>()
+                / keys.len()
+        };
+        let score = if score1 + score2 == 0 {
+            0.5
+        } else {
+            score1 as f64 / (score1 + score2) as f64
+        };
+        let (change1, change2) = self.score_changes(average(&keys1), average(&keys2), score);
+
+        let (result1, result2) = match score1.cmp(&score2) {
+            Ordering::Greater => (GameResult::Win, GameResult::Loss),
+            Ordering::Less => (GameResult::Loss, GameResult::Win),
+            Ordering::Equal => (GameResult::Draw, GameResult::Draw),
+        };
+
+        let now = now();
+        for (keys, change, result) in [(keys1, change1, result1), (keys2, change2, result2)] {
+            for key in keys {
+                let new_rating = self.apply_change(self.players.get(&key).unwrap().rating, change);
+                self.finish_rated_game(&key, new_rating, result, now);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// How many rating points would move from one player to the other if player1 won (or drew)
+    /// against player2, without playing the game, rounded to whole points.
+    #[allow(dead_code)]
+    pub fn points_exchanged(&self, p1: &str, p2: &str, is_draw: bool) -> Result<u32, EloError> {
+        if self.key(p1) == self.key(p2) {
+            return Err(EloError::SelfPlay(p1.to_string()));
+        }
+
+        let player = |name: &str| {
+            self.get_player(name)
+                .ok_or_else(|| EloError::PlayerNotFound(name.to_string()))
+        };
+        let (player1, player2) = (player(p1)?, player(p2)?);
+
+        let (new1, _) = self.new_ratings(player1, player2, is_draw);
+        let multiplier = self.config.precision_multiplier as f64;
+        Ok((new1.abs_diff(player1.rating) as f64 / multiplier).round() as u32)
+    }
+
+    /// The unrounded rating changes (in points) if player1 won (or drew) against player2, to
+    /// audit the rounding done by `add_game`. Missing players count as new players.
+    #[allow(dead_code)]
+    pub fn exact_delta(&self, p1: &str, p2: &str, is_draw: bool) -> (f64, f64) {
+        let new_player = Player::new(String::new(), self.starting_rating(), 0);
+        let player1 = self.get_player(p1).unwrap_or(&new_player);
+        let player2 = self.get_player(p2).unwrap_or(&new_player);
+
+        let (change1, change2) = self.rating_changes(player1, player2, is_draw);
+        let multiplier = self.config.precision_multiplier as f64;
+        (change1 / multiplier, change2 / multiplier)
+    }
+
+    /// Record a game against an opponent of fixed rating (like a bot), which is not added to the
+    /// storage. Only the player's rating and number of games are updated.
+    #[allow(dead_code)]
+    pub fn add_game_vs_rating(
+        &mut self,
+        player: &str,
+        opponent_rating: usize,
+        is_draw: bool,
+        player_won: bool,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        self.check_draw(is_draw)?;
+        self.check_known(&[player])?;
+        self.try_add(player)?;
+        let key = self.key(player).into_owned();
+
+        let opponent = Player::new(
+            String::new(),
+            opponent_rating * self.config.precision_multiplier,
+            0,
+        );
+        let new_rating = if is_draw || player_won {
+            self.new_ratings(self.players.get(&key).unwrap(), &opponent, is_draw)
+                .0
+        } else {
+            self.new_ratings(&opponent, self.players.get(&key).unwrap(), false)
+                .1
+        };
+
+        let result = match (is_draw, player_won) {
+            (true, _) => GameResult::Draw,
+            (false, true) => GameResult::Win,
+            (false, false) => GameResult::Loss,
+        };
+
+        self.finish_rated_game(&key, new_rating, result, now());
+        Ok(())
+    }
+
+    /// Record a game where only the learner is rated, like a coaching match. The learner's rating
+    /// moves as in a game against the coach's current rating, but the coach is left untouched.
+    /// If is_draw is false, learner_won tells who won the game.
+    #[allow(dead_code)]
+    pub fn add_one_sided_game(
+        &mut self,
+        learner: &str,
+        coach: &str,
+        is_draw: bool,
+        learner_won: bool,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        self.check_draw(is_draw)?;
+        let coach = self
+            .get_player(coach)
+            .ok_or_else(|| EloError::PlayerNotFound(coach.to_string()))?
+            .clone();
+        let key = self.key(learner).into_owned();
+        if key == coach.name {
+            return Err(EloError::SelfPlay(learner.to_string()));
+        }
+
+        self.check_known(&[learner])?;
+        self.try_add(learner)?;
+        let new_rating = if is_draw || learner_won {
+            self.new_ratings(self.players.get(&key).unwrap(), &coach, is_draw)
+                .0
+        } else {
+            self.new_ratings(&coach, self.players.get(&key).unwrap(), false)
+                .1
+        };
+
+        let result = match (is_draw, learner_won) {
+            (true, _) => GameResult::Draw,
+            (false, true) => GameResult::Win,
+            (false, false) => GameResult::Loss,
+        };
+        self.finish_rated_game(&key, new_rating, result, now());
+        Ok(())
+    }
+
+    /// Give the player a bye worth `value` points (1 for a full point, 0.5 for a half-point bye),
+    /// as awarded by Swiss pairings. The bye counts as a game, tallied as a win, draw or loss by
+    /// its value, but there is no opponent so the rating does not change.
+    #[allow(dead_code)]
+    pub fn add_bye(&mut self, player: &str, value: f64) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        self.check_known(&[player])?;
+        self.try_add(player)?;
+        let key = self.key(player).into_owned();
+
+        let result = if value >= 1.0 {
+            GameResult::Win
+        } else if value > 0.0 {
+            GameResult::Draw
+        } else {
+            GameResult::Loss
+        };
+        let rating = self.players.get(&key).unwrap().rating;
+        self.finish_rated_game(&key, rating, result, now());
+        Ok(())
+    }
+
+    /// Set the rating of a player after a game and count the game, letting the established hook
+    /// know if this made the player established
+    fn finish_game(&mut self, key: &str, new_rating: usize, result: GameResult) {
+        let window = self.config.recent_results_window;
+        let player = self.players.get_mut(key).unwrap();
+        player.rating = new_rating;
+        player.record_result(result, window);
+        let alpha = self.config.ema_alpha;
+        player.ema_rating = alpha * new_rating as f64 + (1.0 - alpha) * player.ema_rating;
+
+        let multiplier = self.config.precision_multiplier;
+        for &threshold in &self.config.milestones {
+            if new_rating >= threshold * multiplier && !player.milestones.contains(&threshold) {
+                player.milestones.push(threshold);
+            }
+        }
+
+        if player.number_of_games == self.config.provisional_games {
+            if let Some(EstablishedHook(hook)) = &mut self.on_established {
+                hook(player);
+            }
+        }
+    }
+
+    /// Add `points` to the rating of the player outside of any game, like a bonus for winning a
+    /// tournament. The game count is left alone and the bonus is kept in the audit log.
+    #[allow(dead_code)]
+    pub fn award_bonus(&mut self, name: &str, points: usize, reason: &str) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let key = self.key(name).into_owned();
+        let player = self
+            .players
+            .get_mut(&key)
+            .ok_or_else(|| EloError::PlayerNotFound(name.to_string()))?;
+        player.rating += points * self.config.precision_multiplier;
+
+        self.audit_log.push(AuditEntry {
+            player: key,
+            points,
+            reason: reason.to_string(),
+        });
+        Ok(())
+    }
+
+    /// The rating changes made outside of games, oldest first
+    #[allow(dead_code)]
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit_log
+    }
+
+    /// Scale the rating changes of the player by `multiplier`, like a handicap. A multiplier of
+    /// 2 makes their rating move twice as fast as others'. The multiplier must be positive.
+    #[allow(dead_code)]
+    pub fn set_k_multiplier(&mut self, name: &str, multiplier: f64) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        if !(multiplier.is_finite() && multiplier > 0.0) {
+            return Err(EloError::InvalidPlayer(format!(
+                "K multiplier {} is not positive",
+                multiplier
+            )));
+        }
+        let key = self.key(name).into_owned();
+        self.players
+            .get_mut(&key)
+            .ok_or_else(|| EloError::PlayerNotFound(name.to_string()))?
+            .k_multiplier = multiplier;
+        Ok(())
+    }
+
+    /// Reject all new games and other changes to the players with `EloError::PoolLocked` until
+    /// `unlock` is called, for example during maintenance.
+    #[allow(dead_code)]
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    #[allow(dead_code)]
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    #[allow(dead_code)]
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Call `cb` with a player as soon as they have played the configured number of provisional
+    /// games.
+    #[allow(dead_code)]
+    pub fn on_established(&mut self, cb: Box<dyn FnMut(&Player) + Send>) {
+        self.on_established = Some(EstablishedHook(cb));
+    }
+
+    /// Merge the player `other` into `keep`, for when the same person was entered under two names.
+    /// The rating becomes the average of both, weighted by their number of games, and the game
+    /// counts are added together. Labels and milestones are combined, the recent results of
+    /// whoever played last come last, and the longest win streak is the longer of the two.
+    /// The display name, K multiplier, deviation and current win streak of `keep` are kept.
+    /// Recorded games and audit log entries of `other` are attributed to `keep`.
+    #[allow(dead_code)]
+    pub fn absorb(&mut self, keep: &str, other: &str) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let keep = self.key(keep).into_owned();
+        let other = self.key(other).into_owned();
+        if self.players.get(&keep).is_none() {
+            return Err(EloError::PlayerNotFound(keep));
+        }
+        if keep == other {
+            return Ok(());
+        }
+
+        let absorbed = self
+            .players
+            .remove_player(&other)
+            .ok_or_else(|| EloError::PlayerNotFound(other.clone()))?;
+        let player = self.players.get_mut(&keep).unwrap();
+
+        let games = player.number_of_games + absorbed.number_of_games;
+        player.rating = if games == 0 {
+            (player.rating + absorbed.rating) / 2
+        } else {
+            ((player.rating * player.number_of_games + absorbed.rating * absorbed.number_of_games)
+                as f64
+                / games as f64)
+                .round() as usize
+        };
+        player.number_of_games = games;
+        player.wins += absorbed.wins;
+        player.losses += absorbed.losses;
+        player.draws += absorbed.draws;
+        player.longest_win_streak = player.longest_win_streak.max(absorbed.longest_win_streak);
+        player.labels.extend(absorbed.labels);
+        for milestone in absorbed.milestones {
+            if !player.milestones.contains(&milestone) {
+                player.milestones.push(milestone);
+            }
+        }
+
+        let day = |last_played: Option<u64>| last_played.map(|t| t / SECONDS_PER_DAY);
+        if day(absorbed.last_played) == day(player.last_played) {
+            player.games_today += absorbed.games_today;
+        } else if absorbed.last_played > player.last_played {
+            player.games_today = absorbed.games_today;
+        }
+        let mut recent_results = absorbed.recent_results;
+        if absorbed.last_played < player.last_played {
+            std::mem::swap(&mut player.recent_results, &mut recent_results);
+        }
+        player.recent_results.extend(recent_results);
+        player.last_played = player.last_played.max(absorbed.last_played);
+        while player.recent_results.len() > self.config.recent_results_window {
+            player.recent_results.pop_front();
+        }
+
+        let names = self
+            .history
+            .iter_mut()
+            .flat_map(|game| [&mut game.player1, &mut game.player2])
+            .chain(self.audit_log.iter_mut().map(|entry| &mut entry.player));
+        for name in names {
+            if *name == other {
+                name.clone_from(&keep);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rename players from the keys of `mapping` to its values, all at once so names can be
+    /// swapped. Recorded games and the audit log follow the renames. Nothing is renamed if any
+    /// player is missing, would be renamed twice, or two players would end up with the same name.
+    #[allow(dead_code)]
+    pub fn rename_all(&mut self, mapping: &HashMap<String, String>) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let mut renames: Vec<(String, String, String)> = Vec::new();
+        for (from, to) in mapping {
+            if to.trim().is_empty() {
+                return Err(EloError::InvalidPlayer("name is empty".to_string()));
+            }
+            let from = self.key(from).into_owned();
+            if self.players.get(&from).is_none() {
+                return Err(EloError::PlayerNotFound(from));
+            }
+            if renames.iter().any(|(renamed, ..)| *renamed == from) {
+                return Err(EloError::InvalidPlayer(format!(
+                    "{} is renamed twice",
+                    from
+                )));
+            }
+            renames.push((from, self.key(to).into_owned(), to.clone()));
+        }
+
+        let renamed = renames.iter().map(|(from, ..)| from).collect::<Vec<_>>();
+        let mut taken = HashSet::new();
+        for (_, to, display_name) in &renames {
+            let kept = self.players.get(to).is_some() && !renamed.contains(&to);
+            if kept || !taken.insert(to) {
+                return Err(EloError::InvalidPlayer(format!(
+                    "{} is already taken",
+                    display_name
+                )));
+            }
+        }
+
+        let players = renames
+            .iter()
+            .map(|(from, ..)| self.players.remove_player(from).unwrap())
+            .collect::<Vec<_>>();
+        for (mut player, (_, to, display_name)) in players.into_iter().zip(&renames) {
+            player.name.clone_from(to);
+            player.display_name.clone_from(display_name);
+            self.players.add_player(player);
+        }
+
+        let names = self
+            .history
+            .iter_mut()
+            .flat_map(|game| [&mut game.player1, &mut game.player2])
+            .chain(self.audit_log.iter_mut().map(|entry| &mut entry.player));
+        for name in names {
+            if let Some((_, to, _)) = renames.iter().find(|(from, ..)| from == name) {
+                name.clone_from(to);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Groups of player names that are the same after `normalizer`, like trimming and
+    /// lowercasing, which are probably the same person. These can be merged with `absorb`.
+    #[allow(dead_code)]
+    pub fn find_duplicates(&self, normalizer: impl Fn(&str) -> String) -> Vec<Vec<String>> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for player in self.players.all_players() {
+            groups
+                .entry(normalizer(player.name()))
+                .or_default()
+                .push(player.name().to_string());
+        }
+
+        let mut duplicates = groups
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort();
+                names
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        duplicates
+    }
+
+    /// Merge players whose names only differ in case, as a one-off cleanup. Each group is merged
+    /// with `absorb` into the casing with the most games.
+    #[allow(dead_code)]
+    pub fn merge_case_duplicates(&mut self) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        for names in self.find_duplicates(|name| name.to_lowercase()) {
+            let games = |name: &String| self.players.get(name).map_or(0, |p| p.number_of_games);
+            let keep = names
+                .iter()
+                .min_by_key(|name| std::cmp::Reverse(games(name)))
+                .unwrap()
+                .clone();
+
+            for other in names.iter().filter(|&name| *name != keep) {
+                self.absorb(&keep, other)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Give players an initial rating from their historical win rate, like when importing an
+    /// existing league. A win rate of 0.5 gives the current pool mean (or the starting elo for an
+    /// empty pool), and better records are seeded above it. Players are added if missing, and
+    /// nobody is seeded if they would not all fit in the pool.
+    #[allow(dead_code)]
+    pub fn seed_from_winrates(&mut self, records: &[(String, f64)]) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        if let Some(max_players) = self.config.max_players {
+            let new_players = records
+                .iter()
+                .map(|(name, _)| self.key(name))
+                .filter(|key| self.players.get(key).is_none())
+                .collect::<HashSet<_>>()
+                .len();
+            if self.players.all_players().len() + new_players > max_players {
+                return Err(EloError::PoolFull(max_players));
+            }
+        }
+
+        let mean = self.mean_rating().unwrap_or(self.starting_rating() as f64);
+        let multiplier = self.config.precision_multiplier as f64;
+
+        for (name, win_rate) in records {
+            let gap = crate::rating_gap_for_probability(win_rate.clamp(0.01, 0.99));
+            self.try_add(name)?;
+            self[name.as_str()].rating = (mean + gap * multiplier).round().max(0.0) as usize;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the player, and recompute the ratings of everyone else by replaying every recorded
+    /// game not involving them from the starting elo.
+    /// Requires history, otherwise the player is only removed. Like in `recompute_with_config`,
+    /// changes from anything but two-player games are lost.
+    #[allow(dead_code)]
+    pub fn remove_and_recompute(&mut self, name: &str) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let key = self.key(name).into_owned();
+        self.players.remove_player(&key);
+        if !self.config.history {
+            return Ok(());
+        }
+
+        let games = std::mem::take(&mut self.history)
+            .into_iter()
+            .filter(|game| game.player1 != key && game.player2 != key)
+            .collect::<Vec<_>>();
+        self.replay(&games);
+        Ok(())
+    }
+
+    /// The leaderboard of a fresh table with the same config, rating only the recorded games
+    /// played between `start` and `end` (inclusive, in seconds since the unix epoch), like for a
+    /// season. The table itself is not changed. Requires history.
+    #[allow(dead_code)]
+    pub fn ratings_for_window(&self, start: u64, end: u64) -> Vec<Player> {
+        let mut window = self.scratch();
+        window.players.clear();
+        // the players of the window are only added by its games
+        window.config.strict = false;
+
+        for game in self
+            .history
+            .iter()
+            .filter(|game| (start..=end).contains(&game.timestamp))
+        {
+            let _ = window.add_game_at(&game.player1, &game.player2, game.is_draw, game.timestamp);
+        }
+        window.leaderboard().into_iter().cloned().collect()
+    }
+
+    /// Recompute all ratings by replaying the recorded games from the starting elo, with the
+    /// K-factor of each game scaled by the recency decay to the power of how many games ago it
+    /// was played, so recent games count the most. Does nothing without history. Like in
+    /// `recompute_with_config`, changes from anything but two-player games are lost.
+    #[allow(dead_code)]
+    pub fn replay_weighted(&mut self) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        if !self.config.history {
+            return Ok(());
+        }
+
+        let games = std::mem::take(&mut self.history);
+        let decay = self.config.recency_decay;
+        let last = games.len().saturating_sub(1);
+        self.replay_with(&games, |i| decay.powi((last - i) as i32));
+        Ok(())
+    }
+
+    /// Switch to the config of `builder` (its storage is not used) and recompute all ratings from
+    /// the recorded games under it, keeping the same players. History stays enabled whatever
+    /// the new config says, as it is needed for recomputing. Does nothing without history, as
+    /// the ratings could not be recomputed.
+    ///
+    /// If the new config would reject a recorded game, like a draw when draws are no longer
+    /// allowed, its error is returned and nothing is changed. Only two-player games are recorded,
+    /// so team games, games against a rating, byes and bonuses are lost in the recompute.
+    #[allow(dead_code)]
+    pub fn recompute_with_config<T: EloStorage>(
+        &mut self,
+        builder: EloBuilder<T>,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        if !self.config.history {
+            return Ok(());
+        }
+
+        let config = EloConfig {
+            history: self.config.history,
+            ..builder.config
+        };
+        let config = std::mem::replace(&mut self.config, config);
+        let games = self
+            .history
+            .iter()
+            .map(|game| (game.player1.clone(), game.player2.clone(), game.is_draw))
+            .collect::<Vec<_>>();
+        if let Some((_, e)) = self.validate_games(&games).into_iter().next() {
+            self.config = config;
+            return Err(e);
+        }
+
+        let games = std::mem::take(&mut self.history);
+        self.replay(&games);
+        Ok(())
+    }
+
+    /// Reset every player to the starting elo, and play the games again. Display names, labels,
+    /// K multipliers and deviations are kept. The games must be valid under the current config.
+    fn replay(&mut self, games: &[GameRecord]) {
+        self.replay_with(games, |_| 1.0);
+    }
+
+    /// Like `replay`, with the K-factor of the i-th game scaled by `k_weight(i)`
+    fn replay_with(&mut self, games: &[GameRecord], k_weight: impl Fn(usize) -> f64) {
+        let players = self
+            .players
+            .all_players()
+            .into_iter()
+            .map(|p| {
+                let mut player = Player::new(p.name.clone(), self.starting_rating(), 0);
+                player.display_name.clone_from(&p.display_name);
+                player.labels.clone_from(&p.labels);
+                player.k_multiplier = p.k_multiplier;
+                player.deviation = p.deviation;
+                player
+            })
+            .collect::<Vec<_>>();
+        for player in players {
+            self.players.update_player(&player);
+        }
+
+        // players that were established already should not be announced again
+        let on_established = self.on_established.take();
+        let k_factor = self.config.k_factor;
+        self.history.clear();
+        for (i, game) in games.iter().enumerate() {
+            self.config.k_factor = k_factor * k_weight(i);
+            let _ = self.add_game_at(&game.player1, &game.player2, game.is_draw, game.timestamp);
+        }
+        self.config.k_factor = k_factor;
+        self.on_established = on_established;
+    }
+
+    /// The change between two ratings in stored units, in whole points
+    fn points_delta(&self, before: usize, after: usize) -> i64 {
+        let multiplier = self.config.precision_multiplier as f64;
+        ((after as f64 - before as f64) / multiplier).round() as i64
+    }
+
+    /// The rating new players start with, in stored units
+    fn starting_rating(&self) -> usize {
+        self.config.starting_elo * self.config.precision_multiplier
+    }
+
+    /// The expected score between two ratings in stored units
+    fn expected(&self, rating: usize, opponent_rating: usize) -> f64 {
+        let gap = rating as f64 - opponent_rating as f64;
+        self.config
+            .model
+            .expected_score(gap / self.config.precision_multiplier as f64)
+    }
+
+    /// The rating of the player in points, taking the precision multiplier into account.
+    #[allow(dead_code)]
+    pub fn rating_display(&self, name: &str) -> Option<f64> {
+        let player = self.get_player(name)?;
+        Some(player.rating as f64 / self.config.precision_multiplier as f64)
+    }
+
+    /// The unrounded rating changes of the two players when player1 wins (or draws) against
+    /// player2
+    fn rating_changes(&self, player1: &Player, player2: &Player, is_draw: bool) -> (f64, f64) {
+        let score1 = if is_draw { 0.5 } else { 1.0 };
+        let provisional = |player: &Player| player.number_of_games < self.config.provisional_games;
+        let k_factor = |player: &Player| {
+            let k_factor = match self.config.provisional_k_factor {
+                Some(k_factor) if provisional(player) => k_factor,
+                _ => self.config.k_factor,
+            };
+            k_factor * player.k_multiplier
+        };
+        let cap = |player: &Player, change: f64| match self.config.provisional_cap {
+            Some(cap) if provisional(player) => {
+                let cap = cap as f64 * self.config.precision_multiplier as f64;
+                change.clamp(-cap, cap)
+            }
+            _ => change,
+        };
+
+        let (change1, change2) = self.score_changes_with_k(
+            player1.rating,
+            player2.rating,
+            score1,
+            (k_factor(player1), k_factor(player2)),
+        );
+        (cap(player1, change1), cap(player2, change2))
+    }
+
+    /// The unrounded rating changes for two ratings when the first scores `score1` (between 0
+    /// and 1) against the second
+    fn score_changes(&self, rating1: usize, rating2: usize, score1: f64) -> (f64, f64) {
+        let k_factor = self.config.k_factor;
+        self.score_changes_with_k(rating1, rating2, score1, (k_factor, k_factor))
+    }
+
+    /// Like `score_changes`, with a K-factor for each of the players
+    fn score_changes_with_k(
+        &self,
+        rating1: usize,
+        rating2: usize,
+        score1: f64,
+        (k_factor1, k_factor2): (f64, f64),
+    ) -> (f64, f64) {
+        let expected = |rating, opponent_rating| {
+            let expected = self.expected(rating, opponent_rating);
+            match self.config.expected_clamp {
+                Some((min, max)) => expected.clamp(min, max),
+                None => expected,
+            }
+        };
+
+        let scale = self.config.precision_multiplier as f64 * (1.0 - self.config.momentum);
+
+        (
+            k_factor1 * scale * (score1 - expected(rating1, rating2)),
+            k_factor2 * scale * (1.0 - score1 - expected(rating2, rating1)),
+        )
+    }
+
+    /// The ratings of the two players after player1 wins (or draws) against player2
+    fn new_ratings(&self, player1: &Player, player2: &Player, is_draw: bool) -> (usize, usize) {
+        let (change1, change2) = self.rating_changes(player1, player2, is_draw);
+
+        (
+            self.apply_change(player1.rating, change1),
+            self.apply_change(player2.rating, change2),
+        )
+    }
+
+    /// The new rating after an unrounded rating change from a game
+    fn apply_change(&self, rating: usize, change: f64) -> usize {
+        let mut new_rating = rating as f64 + change;
+        new_rating -= self.config.inflation_sink * (new_rating - self.starting_rating() as f64);
+
+        let new_rating = new_rating.round().max(0.0) as usize;
+        match self.config.rating_ceiling {
+            Some(ceiling) => new_rating.min(ceiling * self.config.precision_multiplier),
+            None => new_rating,
+        }
+    }
+
+    /// Check that the table is consistent: every player is stored under their own name, no
+    /// player has more wins, losses and draws than games, and no player has more recorded games
+    /// than games.
+    #[allow(dead_code)]
+    pub fn validate(&self) -> Result<(), String> {
+        let mut recorded: HashMap<&str, usize> = HashMap::new();
+        for game in &self.history {
+            *recorded.entry(&game.player1).or_default() += 1;
+            *recorded.entry(&game.player2).or_default() += 1;
+        }
+
+        for player in self.players.all_players() {
+            if self.key(&player.name) != player.name
+                || self.players.get(&player.name).map(|p| &p.name) != Some(&player.name)
+            {
+                return Err(format!("{} is not stored under its name", player.name));
+            }
+
+            let results = player.wins + player.losses + player.draws;
+            if results > player.number_of_games {
+                return Err(format!(
+                    "{} has {} results but only {} games",
+                    player.name, results, player.number_of_games
+                ));
+            }
+
+            let games = recorded.get(player.name()).copied().unwrap_or(0);
+            if games > player.number_of_games {
+                return Err(format!(
+                    "{} has {} recorded games but only {} games",
+                    player.name, games, player.number_of_games
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the entry for a player, for in-place creation or modification.
+    #[allow(dead_code)]
+    pub fn entry(&mut self, name: &str) -> PlayerEntry<'_, S> {
+        let key = self.key(name).into_owned();
+        if self.players.get(&key).is_some() {
+            PlayerEntry::Occupied(self.players.get_mut(&key).unwrap())
+        } else {
+            PlayerEntry::Vacant(VacantEntry {
+                elo: self,
+                name: name.to_string(),
+            })
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_player(&self, name: &str) -> Option<&Player> {
+        self.players.get(&self.key(name))
+    }
+
+    /// Build a comparator for sorting players, with the given tie-break on equal ratings.
+    #[allow(dead_code)]
+    pub fn comparator(&self, tie_break: TieBreak) -> impl Fn(&&Player, &&Player) -> Ordering {
+        move |a, b| a.cmp_with(b, tie_break)
+    }
+
+    /// All players, sorted with the highest rating first.
+    #[allow(dead_code)]
+    pub fn leaderboard(&self) -> Vec<&Player> {
+        let mut players = self.players.all_players();
+        players.sort();
+        players
+    }
+
+    /// The players with the given label, highest rated first.
+    #[allow(dead_code)]
+    pub fn players_with_label<'a>(&'a self, label: &str) -> Vec<&'a Player> {
+        self.leaderboard()
+            .into_iter()
+            .filter(|p| p.has_label(label))
+            .collect()
+    }
+
+    /// The top `top_n` of the leaderboard as a bordered text table with rank, name, rating and
+    /// games columns, for terminals. Names longer than 16 characters are cut short.
+    #[allow(dead_code)]
+    pub fn to_ascii_table(&self, top_n: usize) -> String {
+        let border = format!(
+            "+------+-{}-+--------+-------+\n",
+            "-".repeat(ASCII_NAME_WIDTH)
+        );
+        let mut table = border.clone();
+        table += &format!(
+            "| Rank | {:<width$} | Rating | Games |\n",
+            "Name",
+            width = ASCII_NAME_WIDTH
+        );
+        table += &border;
+
+        for (i, player) in self.leaderboard().into_iter().take(top_n).enumerate() {
+            let mut name = player.display_name.clone();
+            if name.chars().count() > ASCII_NAME_WIDTH {
+                name = name.chars().take(ASCII_NAME_WIDTH - 3).collect::<String>() + "...";
+            }
+            table += &format!(
+                "| {:>4} | {:<width$} | {:>6} | {:>5} |\n",
+                i + 1,
+                name,
+                player.rating / self.config.precision_multiplier,
+                player.number_of_games,
+                width = ASCII_NAME_WIDTH
+            );
+        }
+
+        if top_n > 0 && !self.players.all_players().is_empty() {
+            table += &border;
+        }
+        table
+    }
+
+    /// All players, sorted by a conservative estimate of their rating, so players with few games
+    /// rank lower than their rating alone would place them. The estimate is the rating minus a
+    /// penalty of `200 / sqrt(games + 1)` points.
+    #[allow(dead_code)]
+    pub fn confident_leaderboard(&self) -> Vec<&Player> {
+        let penalty = CONFIDENCE_PENALTY * self.config.precision_multiplier as f64;
+        let conservative =
+            |p: &Player| p.rating as f64 - penalty / ((p.number_of_games + 1) as f64).sqrt();
+
+        let mut players = self.players.all_players();
+        players.sort_by(|a, b| {
+            conservative(b)
+                .total_cmp(&conservative(a))
+                .then_with(|| a.cmp(b))
+        });
+        players
+    }
+
+    /// The margin p1 is expected to win by against p2 (negative if p1 is expected to lose), taken
+    /// as `points_per_elo` points of score for each rating point between them.
+    #[allow(dead_code)]
+    pub fn expected_margin(&self, p1: &str, p2: &str, points_per_elo: f64) -> Option<f64> {
+        let (player1, player2) = (self.get_player(p1)?, self.get_player(p2)?);
+        let gap = player1.rating as f64 - player2.rating as f64;
+
+        Some(gap / self.config.precision_multiplier as f64 * points_per_elo)
+    }
+
+    /// The head-to-head record between two players as (wins for a, wins for b, draws).
+    /// Only games recorded in the history are counted.
+    #[allow(dead_code)]
+    pub fn head_to_head(&self, a: &str, b: &str) -> (usize, usize, usize) {
+        let (a, b) = (&*self.key(a), &*self.key(b));
+        self.history
+            .iter()
+            .fold((0, 0, 0), |(a_wins, b_wins, draws), game| {
+                match (game.player1.as_str(), game.player2.as_str(), game.is_draw) {
+                    (p1, p2, true) if (p1, p2) == (a, b) || (p1, p2) == (b, a) => {
+                        (a_wins, b_wins, draws + 1)
+                    }
+                    (p1, p2, false) if (p1, p2) == (a, b) => (a_wins + 1, b_wins, draws),
+                    (p1, p2, false) if (p1, p2) == (b, a) => (a_wins, b_wins + 1, draws),
+                    _ => (a_wins, b_wins, draws),
+                }
+            })
+    }
+
+    /// How many recorded games the two players have played against each other, whatever the
+    /// result. Requires history.
+    #[allow(dead_code)]
+    pub fn games_between(&self, a: &str, b: &str) -> usize {
+        let (a_wins, b_wins, draws) = self.head_to_head(a, b);
+        a_wins + b_wins + draws
+    }
+
+    /// How many different opponents the player has faced in the recorded games.
+    /// Requires history.
+    #[allow(dead_code)]
+    pub fn distinct_opponents(&self, name: &str) -> usize {
+        let name = &*self.key(name);
+        self.history
+            .iter()
+            .filter_map(|game| {
+                if game.player1 == name {
+                    Some(&game.player2)
+                } else if game.player2 == name {
+                    Some(&game.player1)
+                } else {
+                    None
+                }
+            })
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// How chaotic the recorded games were: the mean absolute difference between the score of
+    /// player1 and their expected score. Close to 0 when favorites win, higher the more upsets.
+    /// None without recorded games. Requires history.
+    #[allow(dead_code)]
+    pub fn chaos_index(&self) -> Option<f64> {
+        if self.history.is_empty() {
+            return None;
+        }
+
+        let surprise = self
+            .history
+            .iter()
+            .map(|game| {
+                let score = if game.is_draw { 0.5 } else { 1.0 };
+                (score - game.player1_expected).abs()
+            })
+            .sum::<f64>();
+        Some(surprise / self.history.len() as f64)
+    }
+
+    /// Every recorded game, oldest first. Requires history.
+    #[allow(dead_code)]
+    pub fn games(&self) -> impl Iterator<Item = &GameRecord> {
+        self.history.iter()
+    }
+
+    /// Each recorded game as (player1, player2, rating change of player1, rating change of
+    /// player2), oldest first. Requires history.
+    #[allow(dead_code)]
+    pub fn match_log(&self) -> Vec<(&str, &str, i64, i64)> {
+        self.history
+            .iter()
+            .map(|game| {
+                (
+                    game.player1.as_str(),
+                    game.player2.as_str(),
+                    game.player1_delta,
+                    game.player2_delta,
+                )
+            })
+            .collect()
+    }
+
+    /// The most recently recorded game. Requires history.
+    #[allow(dead_code)]
+    pub fn last_game(&self) -> Option<&GameRecord> {
+        self.history.last()
+    }
+
+    /// The ratings of the player after each of their recorded games, oldest first.
+    /// Requires history.
+    #[allow(dead_code)]
+    pub fn rating_history(&self, name: &str) -> Vec<usize> {
+        let name = &*self.key(name);
+        self.history
+            .iter()
+            .filter_map(|game| {
+                if game.player1 == name {
+                    Some(game.player1_rating_after)
+                } else if game.player2 == name {
+                    Some(game.player2_rating_after)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The rating of the player after their `index`-th recorded game, counting from 0.
+    /// Requires history.
+    #[allow(dead_code)]
+    pub fn rating_at_game(&self, name: &str, index: usize) -> Option<usize> {
+        self.rating_history(name).get(index).copied()
+    }
+
+    /// The mean absolute rating change in points per recorded game of the player, or None if they
+    /// have no recorded games. Requires history.
+    #[allow(dead_code)]
+    pub fn avg_rating_change(&self, name: &str) -> Option<f64> {
+        let name = &*self.key(name);
+        let changes = self
+            .history
+            .iter()
+            .filter_map(|game| {
+                if game.player1 == name {
+                    Some(
+                        game.player1_rating_after
+                            .abs_diff(game.player1_rating_before),
+                    )
+                } else if game.player2 == name {
+                    Some(
+                        game.player2_rating_after
+                            .abs_diff(game.player2_rating_before),
+                    )
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if changes.is_empty() {
+            return None;
+        }
+
+        let multiplier = self.config.precision_multiplier as f64;
+        Some(changes.iter().sum::<usize>() as f64 / changes.len() as f64 / multiplier)
+    }
+
+    /// When the player played each of their recorded games and their rating after it, oldest
+    /// first. Requires history.
+    #[allow(dead_code)]
+    pub fn rating_timeline(&self, name: &str) -> Vec<(u64, usize)> {
+        let name = &*self.key(name);
+        self.history
+            .iter()
+            .filter_map(|game| {
+                if game.player1 == name {
+                    Some((game.timestamp, game.player1_rating_after))
+                } else if game.player2 == name {
+                    Some((game.timestamp, game.player2_rating_after))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the rating of the player has varied by at most `threshold` points over their last
+    /// `window` games. Players with fewer than `window` recorded games are not calibrated.
+    /// Requires history.
+    #[allow(dead_code)]
+    pub fn is_calibrated(&self, name: &str, window: usize, threshold: usize) -> Option<bool> {
+        self.get_player(name)?;
+
+        let ratings = self.rating_history(name);
+        if window == 0 || ratings.len() < window {
+            return Some(false);
+        }
+
+        let recent = &ratings[ratings.len() - window..];
+        let max = recent.iter().max().unwrap();
+        let min = recent.iter().min().unwrap();
+        Some(max - min <= threshold * self.config.precision_multiplier)
+    }
+
+    /// Whether the player is stuck at their level: calibrated within `tolerance` points over their
+    /// last `window` games (see `is_calibrated`) while scoring within 15% of half the points in
+    /// them. Requires history.
+    #[allow(dead_code)]
+    pub fn has_plateaued(&self, name: &str, window: usize, tolerance: usize) -> Option<bool> {
+        if !self.is_calibrated(name, window, tolerance)? {
+            return Some(false);
+        }
+
+        let name = &*self.key(name);
+        let scores = self
+            .history
+            .iter()
+            .filter_map(
+                |game| match (game.is_draw, game.player1 == name, game.player2 == name) {
+                    (true, true, _) | (true, _, true) => Some(0.5),
+                    (false, true, _) => Some(1.0),
+                    (false, _, true) => Some(0.0),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<f64>>();
+        let score = scores[scores.len() - window..].iter().sum::<f64>() / window as f64;
+
+        Some((score - 0.5).abs() <= PLATEAU_SCORE_MARGIN)
+    }
+
+    /// Pairs of players who have played each other more than `min_games` times, and more than
+    /// `avg_factor` times the average number of games between pairs that have met.
+    /// Requires history, each pair is given in lexicographic order.
+    #[allow(dead_code)]
+    pub fn suspicious_pairs(&self, min_games: usize, avg_factor: f64) -> Vec<(String, String)> {
+        let mut pairs: HashMap<(&str, &str), usize> = HashMap::new();
+        for game in &self.history {
+            let (a, b) = (game.player1.as_str(), game.player2.as_str());
+            *pairs.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+
+        if pairs.is_empty() {
+            return Vec::new();
+        }
+        let average = self.history.len() as f64 / pairs.len() as f64;
+
+        let mut suspicious = pairs
+            .into_iter()
+            .filter(|&(_, games)| games > min_games && games as f64 > avg_factor * average)
+            .map(|((a, b), _)| (a.to_string(), b.to_string()))
+            .collect::<Vec<_>>();
+        suspicious.sort();
+        suspicious
+    }
+
+    /// The `top_n` won games where the winner was the least expected to win.
+    /// Requires history.
+    #[allow(dead_code)]
+    pub fn biggest_upsets(&self, top_n: usize) -> Vec<UpsetRecord> {
+        let mut upsets = self
+            .history
+            .iter()
+            .filter(|game| !game.is_draw)
+            .map(|game| UpsetRecord {
+                winner: game.player1.clone(),
+                loser: game.player2.clone(),
+                winner_rating: game.player1_rating_before,
+                loser_rating: game.player2_rating_before,
+                winner_expected: game.player1_expected,
+            })
+            .collect::<Vec<_>>();
+
+        upsets.sort_by(|a, b| a.winner_expected.total_cmp(&b.winner_expected));
+        upsets.truncate(top_n);
+        upsets
+    }
+
+    /// Compare the predicted and actual results of the recorded games by binning every game, from
+    /// both players' point of view, into `buckets` equally wide ranges of expected score.
+    /// Each non-empty bucket gives (mean expected score, mean actual score, number of samples),
+    /// and for a well calibrated model the first two are about equal. Requires history.
+    #[allow(dead_code)]
+    pub fn calibration_report(&self, buckets: usize) -> Vec<(f64, f64, usize)> {
+        if buckets == 0 {
+            return Vec::new();
+        }
+
+        let mut sums = vec![(0.0, 0.0, 0); buckets];
+        for game in &self.history {
+            let score1 = if game.is_draw { 0.5 } else { 1.0 };
+            for (expected, score) in [
+                (game.player1_expected, score1),
+                (1.0 - game.player1_expected, 1.0 - score1),
+            ] {
+                let bucket = ((expected * buckets as f64) as usize).min(buckets - 1);
+                let (expected_sum, score_sum, count) = &mut sums[bucket];
+                *expected_sum += expected;
+                *score_sum += score;
+                *count += 1;
+            }
+        }
+
+        sums.into_iter()
+            .filter(|&(_, _, count)| count > 0)
+            .map(|(expected_sum, score_sum, count)| {
+                (expected_sum / count as f64, score_sum / count as f64, count)
+            })
+            .collect()
+    }
+
+    /// Fit Bradley-Terry strengths to the recorded games with `iterations` rounds of iterative
+    /// scaling, a draw counting as half a win for each player. The strengths sum to 1, strongest
+    /// first. Requires history.
+    #[allow(dead_code)]
+    pub fn bradley_terry(&self, iterations: usize) -> Vec<(String, f64)> {
+        let mut names = self
+            .history
+            .iter()
+            .flat_map(|game| [game.player1.as_str(), game.player2.as_str()])
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let index = |name: &str| names.binary_search(&name).unwrap();
+
+        let n = names.len();
+        let mut wins = vec![0.0; n];
+        let mut games = vec![vec![0.0; n]; n];
+        for game in &self.history {
+            let (i, j) = (index(&game.player1), index(&game.player2));
+            if game.is_draw {
+                wins[i] += 0.5;
+                wins[j] += 0.5;
+            } else {
+                wins[i] += 1.0;
+            }
+            games[i][j] += 1.0;
+            games[j][i] += 1.0;
+        }
+
+        let mut strengths = vec![1.0 / n as f64; n];
+        for _ in 0..iterations {
+            let mut next = (0..n)
+                .map(|i| {
+                    let denominator = (0..n)
+                        .filter(|&j| games[i][j] > 0.0)
+                        .map(|j| games[i][j] / (strengths[i] + strengths[j]))
+                        .sum::<f64>();
+                    wins[i] / denominator
+                })
+                .collect::<Vec<_>>();
+
+            let total = next.iter().sum::<f64>();
+            next.iter_mut().for_each(|s| *s /= total);
+            strengths = next;
+        }
+
+        let mut result = names
+            .into_iter()
+            .map(str::to_string)
+            .zip(strengths)
+            .collect::<Vec<_>>();
+        result.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        result
+    }
+
+    /// The leaderboard, but neighbouring players within the configured h2h window of each other
+    /// are ordered by their head-to-head record instead of by rating.
+    /// Requires history to be enabled, otherwise this is the same as `leaderboard`.
+    #[allow(dead_code)]
+    pub fn leaderboard_h2h(&self) -> Vec<&Player> {
+        let mut players = self.leaderboard();
+
+        // bubble players up past close neighbours they have beaten more often than lost to,
+        // bounded so that non-transitive records can't loop forever
+        for _ in 0..players.len() {
+            let mut swapped = false;
+            for i in 1..players.len() {
+                let (upper, lower) = (players[i - 1], players[i]);
+                let window = self.config.h2h_window * self.config.precision_multiplier;
+                if upper.rating.abs_diff(lower.rating) > window {
+                    continue;
+                }
+
+                let (lower_wins, upper_wins, _) = self.head_to_head(lower.name(), upper.name());
+                if lower_wins > upper_wins {
+                    players.swap(i - 1, i);
+                    swapped = true;
+                }
+            }
+
+            if !swapped {
+                break;
+            }
+        }
+
+        players
+    }
+
+    /// The rating needed to be favored against the player, which is their own rating.
+    #[allow(dead_code)]
+    pub fn rating_to_favor(&self, against: &str) -> Option<usize> {
+        self.rating_to_beat(against, 0.5)
+    }
+
+    /// The rating needed for an expected score of `prob` against the player, or None if the
+    /// player doesn't exist or `prob` is not strictly between 0 and 1.
+    #[allow(dead_code)]
+    pub fn rating_to_beat(&self, against: &str, prob: f64) -> Option<usize> {
+        let player = self.get_player(against)?;
+        if !(prob > 0.0 && prob < 1.0) {
+            return None;
+        }
+
+        let gap =
+            self.config.model.gap_for_probability(prob) * self.config.precision_multiplier as f64;
+        Some((player.rating as f64 + gap).round().max(0.0) as usize)
+    }
+
+    /// The expected finishing position (1 is first) of each player given hypothetical true skills
+    /// in rating points: one plus the chance of every other player finishing above them, taken as
+    /// their expected score against each other. Players without a true skill are left out.
+    /// Best expected rank first.
+    #[allow(dead_code)]
+    pub fn expected_ranks(&self, true_skills: &HashMap<String, f64>) -> Vec<(String, f64)> {
+        let skills = self
+            .players
+            .all_players()
+            .into_iter()
+            .filter_map(|p| Some((p.name.clone(), *true_skills.get(&p.name)?)))
+            .collect::<Vec<_>>();
+
+        let mut ranks = skills
+            .iter()
+            .map(|(name, skill)| {
+                let above = skills
+                    .iter()
+                    .filter(|(other, _)| other != name)
+                    .map(|(_, other_skill)| self.config.model.expected_score(other_skill - skill))
+                    .sum::<f64>();
+                (name.clone(), 1.0 + above)
+            })
+            .collect::<Vec<_>>();
+        ranks.sort_by(|(a, rank_a), (b, rank_b)| rank_a.total_cmp(rank_b).then_with(|| a.cmp(b)));
+        ranks
+    }
+
+    /// The expected score of a against b, pulled toward 0.5 by how uncertain their ratings are,
+    /// using Glicko's g(RD) factor on the combined rating deviations. Players without a deviation
+    /// count as certain, which gives the plain logistic expected score.
+    #[allow(dead_code)]
+    pub fn expected_score_uncertain(&self, a: &str, b: &str) -> Option<f64> {
+        let (a, b) = (self.get_player(a)?, self.get_player(b)?);
+        let multiplier = self.config.precision_multiplier as f64;
+        let gap = (a.rating as f64 - b.rating as f64) / multiplier;
+
+        let deviation = |p: &Player| p.deviation.unwrap_or(0) as f64;
+        let combined = (deviation(a).powi(2) + deviation(b).powi(2)).sqrt();
+        let q = std::f64::consts::LN_10 / crate::SCALE;
+        let g = 1.0 / (1.0 + 3.0 * (q * combined / std::f64::consts::PI).powi(2)).sqrt();
+
+        Some(crate::expected_score_for_gap(g * gap))
+    }
+
+    /// The expected score of the player against every other player, closest matchups first.
+    #[allow(dead_code)]
+    pub fn expected_scores_against(&self, name: &str) -> Vec<(String, f64)> {
+        let Some(player) = self.get_player(name) else {
+            return Vec::new();
+        };
+
+        let mut scores = self
+            .players
+            .all_players()
+            .into_iter()
+            .filter(|opponent| opponent.name() != player.name())
+            .map(|opponent| {
+                (
+                    opponent.name().to_string(),
+                    self.expected(player.rating, opponent.rating),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        scores.sort_by(|(_, a), (_, b)| (a - 0.5).abs().total_cmp(&(b - 0.5).abs()));
+        scores
+    }
+
+    /// The probability that player1 wins a best-of-`best_of` series against player2, treating
+    /// their single game expected score as the chance of winning each game.
+    #[allow(dead_code)]
+    pub fn series_win_probability(&self, p1: &str, p2: &str, best_of: usize) -> Option<f64> {
+        if best_of == 0 {
+            return None;
+        }
+
+        let p = self.expected(self.get_player(p1)?.rating, self.get_player(p2)?.rating);
+        let wins_needed = best_of / 2 + 1;
+
+        // the series is decided the same way if all games are played out
+        let mut binomial = 1.0;
+        let mut probability = 0.0;
+        for wins in 0..=best_of {
+            if wins >= wins_needed {
+                probability +=
+                    binomial * p.powi(wins as i32) * (1.0 - p).powi((best_of - wins) as i32);
+            }
+            binomial = binomial * (best_of - wins) as f64 / (wins + 1) as f64;
+        }
+
+        Some(probability)
+    }
+
+    /// How many games `me` needs to play to be rated above `rival`, if they keep scoring
+    /// `assumed_win_rate` against opponents of their own rating while the rival stays put.
+    /// None if they don't get there within 1000 games.
+    #[allow(dead_code)]
+    pub fn games_to_overtake(&self, me: &str, rival: &str, assumed_win_rate: f64) -> Option<usize> {
+        let target = self.get_player(rival)?.rating;
+        let mut rating = self.get_player(me)?.rating;
+
+        for games in 0..=MAX_OVERTAKE_GAMES {
+            if rating > target {
+                return Some(games);
+            }
+            let (change, _) = self.score_changes(rating, rating, assumed_win_rate);
+            rating = self.apply_change(rating, change);
+        }
+        None
+    }
+
+    /// The expected number of games a best-of-`best_of` series between p1 and p2 lasts, with
+    /// their single game expected score as the chance of p1 winning each game. The series stops
+    /// as soon as a player has won more than half of the games.
+    #[allow(dead_code)]
+    pub fn expected_series_games(&self, p1: &str, p2: &str, best_of: usize) -> Option<f64> {
+        if best_of == 0 {
+            return None;
+        }
+
+        let p = self.expected(self.get_player(p1)?.rating, self.get_player(p2)?.rating);
+        let wins_needed = best_of / 2 + 1;
+
+        // the series ends at game k when the winner gets their last needed win in it
+        let mut binomial = 1.0;
+        let mut ended = 0.0;
+        let mut expected = 0.0;
+        for k in wins_needed..=best_of {
+            let losses = (k - wins_needed) as i32;
+            let ends = binomial
+                * (p.powi(wins_needed as i32) * (1.0 - p).powi(losses)
+                    + (1.0 - p).powi(wins_needed as i32) * p.powi(losses));
+            ended += ends;
+            expected += k as f64 * ends;
+            binomial = binomial * k as f64 / (k + 1 - wins_needed) as f64;
+        }
+
+        // with an even number of games the series can end tied after all of them
+        Some(expected + best_of as f64 * (1.0 - ended))
+    }
+
+    /// A copy of the table with the same config but no history, for trying out games
+    fn scratch(&self) -> Elo<HashMap<String, Player>> {
+        let players = self
+            .players
+            .all_players()
+            .into_iter()
+            .map(|p| (p.name.clone(), p.clone()))
+            .collect();
+
+        Elo {
+            players,
+            config: EloConfig {
+                history: false,
+                ..self.config.clone()
+            },
+            history: Vec::new(),
+            on_established: None,
+            locked: false,
+            audit_log: Vec::new(),
+        }
+    }
+
+    /// Split the table into the players matching `pred` and the rest, both with the same config.
+    /// Recorded games go to the side with both of their players, games between the sides are
+    /// dropped.
+    #[allow(dead_code, clippy::type_complexity)]
+    pub fn partition<F: Fn(&Player) -> bool>(
+        self,
+        pred: F,
+    ) -> (Elo<HashMap<String, Player>>, Elo<HashMap<String, Player>>) {
+        let (mut matching, mut rest) = (self.scratch(), self.scratch());
+        for player in self.players.all_players() {
+            let other = if pred(player) {
+                &mut rest
+            } else {
+                &mut matching
+            };
+            other.players.remove(&player.name);
+        }
+
+        for elo in [&mut matching, &mut rest] {
+            elo.config.history = self.config.history;
+            elo.history = self
+                .history
+                .iter()
+                .filter(|game| {
+                    elo.players.contains_key(&game.player1)
+                        && elo.players.contains_key(&game.player2)
+                })
+                .cloned()
+                .collect();
+        }
+        (matching, rest)
+    }
+
+    /// The points each player is expected to score over the `schedule` at the current ratings.
+    /// Games are drawn as often as in the games played so far, with the rest of each expected
+    /// score coming from wins. Players not in the table yet are counted at the starting elo.
+    #[allow(dead_code)]
+    pub fn projected_points(
+        &self,
+        schedule: &[(String, String)],
+        win_points: f64,
+        draw_points: f64,
+    ) -> HashMap<String, f64> {
+        let players = self.players.all_players();
+        let results = players
+            .iter()
+            .map(|p| p.wins + p.losses + p.draws)
+            .sum::<usize>();
+        let draw_rate = if results == 0 {
+            0.0
+        } else {
+            players.iter().map(|p| p.draws).sum::<usize>() as f64 / results as f64
+        };
+
+        let rating = |name: &str| {
+            self.get_player(name)
+                .map_or(self.starting_rating(), |p| p.rating)
+        };
+        let mut points = HashMap::new();
+        for (p1, p2) in schedule {
+            let expected = self.expected(rating(p1), rating(p2));
+            // a draw is half of the expected score, so there can't be more draws than that allows
+            let draws = draw_rate.min(2.0 * expected.min(1.0 - expected));
+            for (name, expected) in [(p1, expected), (p2, 1.0 - expected)] {
+                *points.entry(self.key(name).into_owned()).or_default() +=
+                    (expected - draws / 2.0) * win_points + draws * draw_points;
+            }
+        }
+        points
+    }
+
+    /// The leaderboard after a round of games, where the higher rated player of each pairing is
+    /// assumed to win. The table itself is not changed.
+    #[allow(dead_code)]
+    pub fn project_round(&self, pairings: &[(String, String)]) -> Vec<Player> {
+        let mut projected = self.scratch();
+        for (p1, p2) in pairings {
+            let rating = |name| projected.get_player(name).map_or(0, |p: &Player| p.rating);
+            let (winner, loser) = if rating(p1) >= rating(p2) {
+                (p1, p2)
+            } else {
+                (p2, p1)
+            };
+            let _ = projected.add_game(winner, loser, false);
+        }
+
+        projected.leaderboard().into_iter().cloned().collect()
+    }
+
+    /// The probability of each player finishing first when the `remaining` games (in any order
+    /// of the pair) are simulated `runs` times, with winners drawn from the expected scores.
+    /// Standings count a point for each win and half a point for each draw, both the games played
+    /// so far and the simulated ones, and shared first places split the title.
+    #[cfg(feature = "rand")]
+    #[allow(dead_code)]
+    pub fn simulate_season(
+        &self,
+        remaining: &[(String, String)],
+        runs: usize,
+        rng: &mut impl rand::Rng,
+    ) -> HashMap<String, f64> {
+        let mut titles = self
+            .players
+            .all_players()
+            .into_iter()
+            .map(|p| (p.name.clone(), 0.0))
+            .collect::<HashMap<_, _>>();
+
+        for _ in 0..runs {
+            let mut season = self.scratch();
+            for (p1, p2) in remaining {
+                let _ = season.try_add(p1);
+                let _ = season.try_add(p2);
+                let (Some(player1), Some(player2)) = (season.get_player(p1), season.get_player(p2))
+                else {
+                    continue;
+                };
+
+                let expected = season.expected(player1.rating, player2.rating);
+                let (winner, loser) = if rng.gen::<f64>() < expected {
+                    (p1, p2)
+                } else {
+                    (p2, p1)
+                };
+                let _ = season.add_game(winner, loser, false);
+            }
+
+            let points = |p: &Player| p.wins as f64 + p.draws as f64 / 2.0;
+            let best = season.players.values().map(points).fold(f64::MIN, f64::max);
+            let champions = season
+                .players
+                .values()
+                .filter(|p| points(p) == best)
+                .collect::<Vec<_>>();
+            for champion in &champions {
+                *titles.entry(champion.name.clone()).or_default() +=
+                    1.0 / champions.len() as f64 / runs as f64;
+            }
+        }
+
+        titles
+    }
+
+    /// The probability of the player truly being at each rank (index 0 is first), simulated `runs`
+    /// times by drawing every player's skill around their rating. The spread is their rating
+    /// deviation when tracked, or else shrinks with games played like in
+    /// `confident_leaderboard`. Empty if the player doesn't exist.
+    #[cfg(feature = "rand")]
+    #[allow(dead_code)]
+    pub fn rank_probabilities(
+        &self,
+        name: &str,
+        runs: usize,
+        rng: &mut impl rand::Rng,
+    ) -> Vec<f64> {
+        let Some(player) = self.get_player(name) else {
+            return Vec::new();
+        };
+        let multiplier = self.config.precision_multiplier as f64;
+        let spread = |p: &Player| match p.deviation {
+            Some(deviation) => deviation as f64 * multiplier,
+            None => CONFIDENCE_PENALTY * multiplier / ((p.number_of_games + 1) as f64).sqrt(),
+        };
+
+        let players = self.players.all_players();
+        let mut counts = vec![0; players.len()];
+        for _ in 0..runs {
+            let mut draw = |p: &Player| {
+                let z = crate::normal_quantile(rng.gen_range(f64::EPSILON..1.0));
+                p.rating as f64 + z * spread(p)
+            };
+            let skill = draw(player);
+            let above = players
+                .iter()
+                .filter(|p| p.name != player.name)
+                .filter(|p| draw(p) > skill)
+                .count();
+            counts[above] += 1;
+        }
+
+        counts
+            .into_iter()
+            .map(|count| count as f64 / runs.max(1) as f64)
+            .collect()
+    }
+
+    /// How many places each player in the pairings is expected to climb (or fall, if negative)
+    /// on the leaderboard after the round, as projected by `project_round`.
+    #[allow(dead_code)]
+    pub fn projected_rank_changes(&self, pairings: &[(String, String)]) -> Vec<(String, i64)> {
+        let current = self.leaderboard();
+        let projected = self.project_round(pairings);
+        let rank = |players: &[&Player], name: &str| players.iter().position(|p| p.name == name);
+
+        let mut names = pairings
+            .iter()
+            .flat_map(|(p1, p2)| [self.key(p1).into_owned(), self.key(p2).into_owned()])
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        let projected = projected.iter().collect::<Vec<_>>();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let before = rank(&current, &name).unwrap_or(current.len()) as i64;
+                let after = rank(&projected, &name)? as i64;
+                Some((name, before - after))
+            })
+            .collect()
+    }
+
+    /// The lower bound of the Wilson score interval for the proportion of games the player has
+    /// won, at the given two-sided `confidence` (like 0.95). A draw counts as half a win. None if
+    /// the player doesn't exist or `confidence` is not strictly between 0 and 1.
+    #[allow(dead_code)]
+    pub fn win_rate_lower_bound(&self, name: &str, confidence: f64) -> Option<f64> {
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return None;
+        }
+        let player = self.get_player(name)?;
+        let n = (player.wins + player.losses + player.draws) as f64;
+        if n == 0.0 {
+            return Some(0.0);
+        }
+
+        let p = (player.wins as f64 + player.draws as f64 / 2.0) / n;
+        let z = crate::normal_quantile(1.0 - (1.0 - confidence) / 2.0);
+        let z2 = z * z;
+
+        Some(
+            (p + z2 / (2.0 * n) - z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt())
+                / (1.0 + z2 / n),
+        )
+    }
+
+    /// Players who have not played since `since_secs` before `now`, or who have never played,
+    /// highest rated first.
+    #[allow(dead_code)]
+    pub fn inactive_players(&self, now: u64, since_secs: u64) -> Vec<&Player> {
+        let cutoff = now.saturating_sub(since_secs);
+        self.leaderboard()
+            .into_iter()
+            .filter(|p| p.last_played.is_none_or(|last_played| last_played < cutoff))
+            .collect()
+    }
+
+    /// Decay the players who have not played in the `periods_elapsed` rating periods before
+    /// `now` (or never played). Players with a rating deviation have it widened once per elapsed
+    /// period, like in Glicko, up to 350. The others are pulled toward the pool mean instead,
+    /// removing the configured period decay of the distance once per elapsed period. `now` is
+    /// taken rather than read from the clock, like in `inactive_players`, as it decides who is
+    /// inactive.
+    #[allow(dead_code)]
+    pub fn apply_period_decay(&mut self, now: u64, periods_elapsed: u32) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let Some(mean) = self.mean_rating() else {
+            return Ok(());
+        };
+        let since = self.config.rating_period * periods_elapsed as u64;
+        let factor = (1.0 - self.config.period_decay).powi(periods_elapsed as i32);
+
+        let inactive = self
+            .inactive_players(now, since)
+            .into_iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        for name in inactive {
+            let player = self.players.get_mut(&name).unwrap();
+            match player.deviation {
+                Some(deviation) => {
+                    let widened = (deviation as f64).powi(2)
+                        + periods_elapsed as f64 * DEVIATION_GROWTH.powi(2);
+                    player.deviation = Some(widened.sqrt().min(MAX_DEVIATION).round() as usize);
+                }
+                None => {
+                    let decayed = mean + (player.rating as f64 - mean) * factor;
+                    player.rating = decayed.round().max(0.0) as usize;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pull the rating of a player returning after `months_away` months toward the pool mean, by
+    /// the configured period decay per month but at most `max_pull` points, so a stale rating
+    /// doesn't stick.
+    #[allow(dead_code)]
+    pub fn decay_returning_player(
+        &mut self,
+        name: &str,
+        months_away: u32,
+        max_pull: usize,
+    ) -> Result<(), EloError> {
+        self.check_unlocked()?;
+        let mean = self
+            .mean_rating()
+            .ok_or_else(|| EloError::PlayerNotFound(name.to_string()))?;
+        let key = self.key(name).into_owned();
+        let multiplier = self.config.precision_multiplier as f64;
+        let factor = 1.0 - (1.0 - self.config.period_decay).powi(months_away as i32);
+        let player = self
+            .players
+            .get_mut(&key)
+            .ok_or_else(|| EloError::PlayerNotFound(name.to_string()))?;
+
+        let max_pull = max_pull as f64 * multiplier;
+        let pull = ((mean - player.rating as f64) * factor).clamp(-max_pull, max_pull);
+        player.rating = (player.rating as f64 + pull).round().max(0.0) as usize;
+        Ok(())
+    }
+
+    /// The number of players per rating bucket of `bucket_size` points, as (lowest rating in the
+    /// bucket, players), covering every bucket from the lowest to the highest rated player.
+    #[allow(dead_code)]
+    pub fn histogram(&self, bucket_size: usize) -> Vec<(usize, usize)> {
+        let buckets = self
+            .players
+            .all_players()
+            .into_iter()
+            .map(|p| p.rating / self.config.precision_multiplier / bucket_size.max(1))
+            .collect::<Vec<_>>();
+        let (Some(&min), Some(&max)) = (buckets.iter().min(), buckets.iter().max()) else {
+            return Vec::new();
+        };
+
+        let mut counts = vec![0; max - min + 1];
+        for bucket in buckets {
+            counts[bucket - min] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| ((min + i) * bucket_size.max(1), count))
+            .collect()
+    }
+
+    /// The Gini coefficient of the ratings in the pool, 0 when everyone has the same rating
+    /// and approaching 1 the more unequal the ratings are.
+    #[allow(dead_code)]
+    pub fn rating_gini(&self) -> f64 {
+        let mut ratings = self
+            .players
+            .all_players()
+            .into_iter()
+            .map(|p| p.rating as f64)
+            .collect::<Vec<_>>();
+        let n = ratings.len() as f64;
+        let total = ratings.iter().sum::<f64>();
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        // with sorted ratings, the sum of all pairwise differences is a weighted sum
+        ratings.sort_by(f64::total_cmp);
+        let weighted = ratings
+            .iter()
+            .enumerate()
+            .map(|(i, rating)| (2.0 * (i + 1) as f64 - n - 1.0) * rating)
+            .sum::<f64>();
+
+        weighted / (n * total)
+    }
+
+    /// The rating of the player on a 0-100 scale between the lowest (0) and highest (100) rated
+    /// players of the pool. If everyone has the same rating, they all get 50.
+    #[allow(dead_code)]
+    pub fn skill_percent(&self, name: &str) -> Option<f64> {
+        let rating = self.get_player(name)?.rating;
+        let players = self.players.all_players();
+        let max = players.iter().map(|p| p.rating).max()?;
+        let min = players.iter().map(|p| p.rating).min()?;
+
+        if max == min {
+            return Some(50.0);
+        }
+        Some((rating - min) as f64 / (max - min) as f64 * 100.0)
+    }
+
+    /// Save the current rating of every player, to compare against later with `diff`.
+    #[allow(dead_code)]
+    pub fn snapshot(&self) -> EloSnapshot {
+        EloSnapshot {
+            ratings: self
+                .players
+                .all_players()
+                .into_iter()
+                .map(|p| (p.name.clone(), p.rating))
+                .collect(),
+            starting_rating: self.starting_rating(),
+            precision_multiplier: self.config.precision_multiplier,
+        }
+    }
+
+    /// The mean rating of all players, or None if there are no players.
+    #[allow(dead_code)]
+    pub fn mean_rating(&self) -> Option<f64> {
+        let players = self.players.all_players();
+        if players.is_empty() {
+            return None;
+        }
+
+        Some(players.iter().map(|p| p.rating as f64).sum::<f64>() / players.len() as f64)
+    }
+
+    /// The rating of the player corrected for the drift of the pool mean away from `era_mean`,
+    /// so ratings from different eras can be compared. `era_mean` and the result are in points.
+    #[allow(dead_code)]
+    pub fn era_adjusted_rating(&self, name: &str, era_mean: usize) -> Option<i64> {
+        let player = self.get_player(name)?;
+        let multiplier = self.config.precision_multiplier as f64;
+        let drift = self.mean_rating()? - era_mean as f64 * multiplier;
+
+        Some(((player.rating as f64 - drift) / multiplier).round() as i64)
+    }
+
+    /// The (population) standard deviation of all ratings, or None if there are no players.
+    #[allow(dead_code)]
+    pub fn rating_std_dev(&self) -> Option<f64> {
+        let mean = self.mean_rating()?;
+        let players = self.players.all_players();
+        let variance = players
+            .iter()
+            .map(|p| (p.rating as f64 - mean).powi(2))
+            .sum::<f64>()
+            / players.len() as f64;
+
+        Some(variance.sqrt())
+    }
+
+    /// How many standard deviations the rating of the player is from the pool mean. A pool where
+    /// everyone has the same rating gives 0.
+    #[allow(dead_code)]
+    pub fn rating_zscore(&self, name: &str) -> Option<f64> {
+        let player = self.get_player(name)?;
+        let (mean, std_dev) = (self.mean_rating()?, self.rating_std_dev()?);
+        if std_dev == 0.0 {
+            return Some(0.0);
+        }
+
+        Some((player.rating as f64 - mean) / std_dev)
+    }
+
+    /// The config and players in a compact binary form, see `Elo::from_bincode`.
+    /// History and hooks are not included.
+    #[cfg(feature = "bincode")]
+    #[allow(dead_code)]
+    pub fn to_bincode(&self) -> Vec<u8> {
+        bincode::serialize(&(&self.config, self.players.all_players()))
+            .expect("players always serialize")
+    }
+
+    #[allow(dead_code)]
+    pub fn storage(&self) -> &S {
+        &self.players
+    }
+
+    #[allow(dead_code)]
+    pub fn storage_mut(&mut self) -> &mut S {
+        &mut self.players
+    }
+
+    #[allow(dead_code)]
+    pub fn into_storage(self) -> S {
+        self.players
+    }
+}
+
+/// A view into a single player of an `Elo`, which may or may not exist yet.
+pub enum PlayerEntry<'a, S: EloStorage> {
+    Occupied(&'a mut Player),
+    Vacant(VacantEntry<'a, S>),
+}
+
+pub struct VacantEntry<'a, S: EloStorage> {
+    elo: &'a mut Elo<S>,
+    name: String,
+}
+
+impl<'a, S: EloStorage> PlayerEntry<'a, S> {
+    /// Get the player, creating it with the starting elo if it does not exist.
+    pub fn or_create(self) -> Result<&'a mut Player, EloError> {
+        match self {
+            PlayerEntry::Occupied(player) => Ok(player),
+            PlayerEntry::Vacant(entry) => entry.insert(),
+        }
+    }
+
+    /// Modify the player in place if it exists.
+    pub fn and_modify<F: FnOnce(&mut Player)>(self, f: F) -> Self {
+        match self {
+            PlayerEntry::Occupied(player) => {
+                f(player);
+                PlayerEntry::Occupied(player)
+            }
+            vacant => vacant,
+        }
+    }
+}
+
+impl<'a, S: EloStorage> VacantEntry<'a, S> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Create the player with the starting elo.
+    pub fn insert(self) -> Result<&'a mut Player, EloError> {
+        self.elo.add_player(&self.name)?;
+        let key = self.elo.key(&self.name).into_owned();
+        Ok(self.elo.players.get_mut(&key).unwrap())
+    }
+}
+
+impl<S: EloStorage> Index<&str> for Elo<S> {
+    type Output = Player;
+
+    fn index(&self, name: &str) -> &Player {
+        self.get_player(name).unwrap()
+    }
+}
+
+impl<S: EloStorage> IndexMut<&str> for Elo<S> {
+    fn index_mut(&mut self, name: &str) -> &mut Player {
+        let key = self.key(name).into_owned();
+        self.players.get_mut(&key).unwrap()
+    }
+}
+
+/// The ratings of every player at one point in time, see `Elo::snapshot`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EloSnapshot {
+    ratings: HashMap<String, usize>,
+    starting_rating: usize,
+    precision_multiplier: usize,
+}
+
+impl EloSnapshot {
+    pub fn rating(&self, name: &str) -> Option<usize> {
+        self.ratings.get(name).copied()
+    }
+}
+
+/// The rating change in points of every player whose rating differs between two snapshots,
+/// sorted by name. Players that are new in `after` are measured from the starting elo.
+pub fn diff(before: &EloSnapshot, after: &EloSnapshot) -> Vec<(String, i64)> {
+    let multiplier = after.precision_multiplier as f64;
+    let mut changes = after
+        .ratings
+        .iter()
+        .map(|(name, &rating)| {
+            let old = before.rating(name).unwrap_or(after.starting_rating);
+            let change = (rating as f64 - old as f64) / multiplier;
+            (name.clone(), change.round() as i64)
+        })
+        .filter(|&(_, change)| change != 0)
+        .collect::<Vec<_>>();
+    changes.sort();
+    changes
+}
+
+/// Replay the games (player1, player2, is_draw) on a fresh table for each K-factor, to see how
+/// much the choice of K affects the outcome. Games where a player plays against themselves are
+/// skipped.
+pub fn sensitivity(games: &[(String, String, bool)], k_values: &[f64]) -> Vec<(f64, Vec<Player>)> {
+    k_values
+        .iter()
+        .map(|&k_factor| {
+            let mut elo = EloBuilder::new(HashMap::new()).k_factor(k_factor).build();
+            for (player1, player2, is_draw) in games {
+                let _ = elo.add_game(player1, player2, *is_draw);
+            }
+
+            let players = elo.leaderboard().into_iter().cloned().collect();
+            (k_factor, players)
+        })
+        .collect()
+}
+
+/// Pick the K-factor among `candidates` that best predicts the games (player1, player2, is_draw),
+/// measured by the log-loss of the expected score before each game against the actual result.
+/// Games where a player plays against themselves are skipped.
+///
+/// # Panics
+/// If `candidates` is empty.
+pub fn optimal_k(games: &[(String, String, bool)], candidates: &[f64]) -> f64 {
+    let log_loss = |k_factor: f64| {
+        let mut elo = EloBuilder::new(HashMap::new()).k_factor(k_factor).build();
+        let mut loss = 0.0;
+        for (player1, player2, is_draw) in games {
+            if elo.key(player1) == elo.key(player2) {
+                continue;
+            }
+            for player in [player1, player2] {
+                let _ = elo.try_add(player);
+            }
+
+            let expected = elo
+                .expected(elo[player1.as_str()].rating, elo[player2.as_str()].rating)
+                .clamp(1e-9, 1.0 - 1e-9);
+            let score = if *is_draw { 0.5 } else { 1.0 };
+            loss -= score * expected.ln() + (1.0 - score) * (1.0 - expected).ln();
+
+            let _ = elo.add_game(player1, player2, *is_draw);
+        }
+        loss
+    };
+
+    candidates
+        .iter()
+        .map(|&k_factor| (k_factor, log_loss(k_factor)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(k_factor, _)| k_factor)
+        .expect("no candidate K-factors")
+}
+
+/// Spearman's rank correlation between the leaderboards of two tables over the players they
+/// share: 1 when they rank them the same, -1 when reversed. Also 1 when they share fewer than
+/// two players, as there is nothing to disagree on.
+pub fn ranking_correlation<S: EloStorage, T: EloStorage>(a: &Elo<S>, b: &Elo<T>) -> f64 {
+    let ranks = |leaderboard: Vec<&Player>, other: &dyn Fn(&str) -> bool| {
+        leaderboard
+            .into_iter()
+            .filter(|p| other(&p.name))
+            .enumerate()
+            .map(|(rank, p)| (p.name.clone(), rank as f64))
+            .collect::<HashMap<_, _>>()
+    };
+    let ranks_a = ranks(a.leaderboard(), &|name| b.players.get(name).is_some());
+    let ranks_b = ranks(b.leaderboard(), &|name| a.players.get(name).is_some());
+
+    let n = ranks_a.len() as f64;
+    if n < 2.0 {
+        return 1.0;
+    }
+    let squared = ranks_a
+        .iter()
+        .map(|(name, rank)| (rank - ranks_b[name]).powi(2))
+        .sum::<f64>();
+    1.0 - 6.0 * squared / (n * (n * n - 1.0))
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct LichessPlayer {
+    username: String,
+    rating: usize,
+    games: usize,
+}
+
+impl Elo<HashMap<String, Player>> {
+    /// Load a table written by `Elo::to_bincode`.
+    #[cfg(feature = "bincode")]
+    #[allow(dead_code)]
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, EloError> {
+        let (config, players): (EloConfig, Vec<Player>) =
+            bincode::deserialize(bytes).map_err(|e| EloError::ParseError(e.to_string()))?;
+
+        let multiplier = config.precision_multiplier;
+        let mut builder = EloBuilder::new(HashMap::new());
+        builder.config = config;
+        // the same normalisation as the builder, as a multiplier of 0 would divide by zero
+        let mut elo = builder.precision_multiplier(multiplier).build();
+        for player in players {
+            elo.players.add_player(player);
+        }
+        Ok(elo)
+    }
+
+    /// Import players from a list of online ratings, in the form
+    /// `[{"username": "a", "rating": 1500, "games": 20}]`.
+    #[cfg(feature = "serde")]
+    #[allow(dead_code)]
+    pub fn from_lichess_json(json: &str) -> Result<Self, EloError> {
+        let players: Vec<LichessPlayer> =
+            serde_json::from_str(json).map_err(|e| EloError::ParseError(e.to_string()))?;
+
+        let mut elo = Elo::new(HashMap::new());
+        for player in players {
+            elo.players
+                .add_player(Player::new(player.username, player.rating, player.games));
+        }
+
+        Ok(elo)
+    }
+
+    /// Rate items from a matrix of pairwise win counts, where `wins[i][j]` is how many times
+    /// `items[i]` beat `items[j]`. The wins of each pair are interleaved and played as games.
+    #[allow(dead_code)]
+    pub fn from_pairwise_counts(items: &[&str], wins: &[Vec<u32>]) -> Self {
+        let mut elo = Elo::new(HashMap::new());
+        for item in items {
+            let _ = elo.try_add(item);
+        }
+
+        let count =
+            |i: usize, j: usize| wins.get(i).and_then(|row| row.get(j)).copied().unwrap_or(0);
+
+        for i in 0..items.len() {
+            for j in i + 1..items.len() {
+                let (mut i_wins, mut j_wins) = (count(i, j), count(j, i));
+                while i_wins > 0 || j_wins > 0 {
+                    if i_wins > 0 {
+                        let _ = elo.add_game(items[i], items[j], false);
+                        i_wins -= 1;
+                    }
+                    if j_wins > 0 {
+                        let _ = elo.add_game(items[j], items[i], false);
+                        j_wins -= 1;
+                    }
+                }
+            }
+        }
+
+        elo
+    }
+}
+
+impl IntoIterator for Elo<HashMap<String, Player>> {
+    type Item = Player;
+    type IntoIter = std::collections::hash_map::IntoValues<String, Player>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.players.into_values()
+    }
+}
+
+impl<'a> IntoIterator for &'a Elo<HashMap<String, Player>> {
+    type Item = &'a Player;
+    type IntoIter = std::collections::hash_map::Values<'a, String, Player>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.players.values()
+    }
+}
+
+/// Build a table with the default config from players with their ratings and games kept.
+impl FromIterator<Player> for Elo<HashMap<String, Player>> {
+    fn from_iter<I: IntoIterator<Item = Player>>(players: I) -> Self {
+        let players = players
+            .into_iter()
+            .map(|player| (player.name.clone(), player))
+            .collect();
+        Elo::new(players)
+    }
+}
+
+impl EloStorage for HashMap<String, Player> {
+    fn add_player(&mut self, player: Player) {
+        self.insert(player.name.clone(), player);
+    }
+
+    fn update_player(&mut self, player: &Player) {
+        self.insert(player.name().to_string(), player.clone());
+    }
+
+    fn get(&self, name: &str) -> Option<&Player> {
+        self.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Player> {
+        self.get_mut(name)
+    }
+
+    fn all_players(&self) -> Vec<&Player> {
+        self.values().collect()
+    }
+
+    fn remove_player(&mut self, name: &str) -> Option<Player> {
+        self.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn single_no_friends() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+
+        assert!(elo.add_game("a", "a", false).is_err());
+    }
+
+    #[test]
+    fn dual() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+
+        assert_eq!(elo["a"].rating(), 999);
+        assert_eq!(elo["b"].rating(), 1001);
+
+        assert_eq!(elo["a"].number_of_games(), 2);
+        assert_eq!(elo["b"].number_of_games(), 2);
+    }
+
+    #[test]
+    fn dual_draw() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+
+        elo.add_game("a", "b", true).unwrap();
+
+        assert_eq!(elo["a"].rating(), 1000);
+        assert_eq!(elo["b"].rating(), 1000);
+    }
+
+    #[test]
+    fn entry_or_create() {
+        let mut elo = Elo::new(HashMap::new());
+
+        elo.entry("new").or_create().unwrap().rating = 1200;
+        elo.entry("new").and_modify(|p| p.number_of_games += 1);
+
+        let hm = elo.into_storage();
+        assert_eq!(hm.len(), 1);
+        assert_eq!(hm["new"].rating(), 1200);
+        assert_eq!(hm["new"].number_of_games(), 1);
+    }
+
+    #[test]
+    fn tie_break() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].number_of_games = 5;
+
+        let mut players = [&elo["a"], &elo["b"]];
+        players.sort_by(elo.comparator(TieBreak::FewerGamesFirst));
+        assert_eq!(players[0].name(), "b");
+        players.sort_by(elo.comparator(TieBreak::MoreGamesFirst));
+        assert_eq!(players[0].name(), "a");
+    }
+
+    #[test]
+    fn leaderboard_h2h() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+
+        // b beats a twice, then a beats c a few times to end up just above b
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo["b"].rating = elo["a"].rating - 5;
+
+        assert_eq!(elo.head_to_head("a", "b"), (0, 2, 0));
+
+        let leaderboard = elo.leaderboard();
+        assert_eq!(leaderboard[0].name(), "a");
+        assert_eq!(leaderboard[1].name(), "b");
+
+        let leaderboard = elo.leaderboard_h2h();
+        assert_eq!(leaderboard[0].name(), "b");
+        assert_eq!(leaderboard[1].name(), "a");
+        assert_eq!(leaderboard[2].name(), "c");
+    }
+
+    #[test]
+    fn expected_scores_against() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo.add_player("weak").unwrap();
+        elo["b"].rating = 1050;
+        elo["weak"].rating = 200;
+
+        let scores = elo.expected_scores_against("a");
+        assert_eq!(scores.len(), 2);
+        assert!(scores.iter().all(|(name, _)| name != "a"));
+
+        assert_eq!(scores[0].0, "b");
+        assert_eq!(scores[1].0, "weak");
+        assert!(scores[1].1 > 0.99);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .case_insensitive(true)
+            .build();
+        elo.add_player("Alice").unwrap();
+        elo.add_game("alice", "Bob", false).unwrap();
+
+        assert!(elo.add_game("ALICE", "alice", false).is_err());
+        assert_eq!(elo["ALICE"].number_of_games(), 1);
+        assert_eq!(elo["alice"].display_name(), "Alice");
+
+        let hm = elo.into_storage();
+        assert_eq!(hm.len(), 2);
+    }
+
+    #[test]
+    fn era_adjusted_rating() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].rating = 1300;
+        elo["b"].rating = 1100;
+
+        // the pool mean is 1200, 200 above the era mean
+        assert_eq!(elo.mean_rating(), Some(1200.0));
+        assert_eq!(elo.era_adjusted_rating("a", 1000), Some(1100));
+        assert_eq!(elo.era_adjusted_rating("b", 1000), Some(900));
+        assert_eq!(elo.era_adjusted_rating("c", 1000), None);
+
+        // the era mean and the result are in points
+        let mut elo = EloBuilder::new(HashMap::new())
+            .precision_multiplier(10)
+            .build();
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].rating = 13000;
+        elo["b"].rating = 11000;
+        assert_eq!(elo.era_adjusted_rating("a", 1000), Some(1100));
+    }
+
+    #[test]
+    fn sensitivity() {
+        let games = [("a", "b", false), ("a", "c", false), ("b", "c", false)]
+            .map(|(p1, p2, is_draw)| (p1.to_string(), p2.to_string(), is_draw));
+
+        let results = super::sensitivity(&games, &[16.0, 32.0]);
+        assert_eq!(results.len(), 2);
+
+        let spread = |players: &[Player]| {
+            players.first().unwrap().rating() - players.last().unwrap().rating()
+        };
+        assert_eq!(results[0].0, 16.0);
+        assert_eq!(results[1].0, 32.0);
+        assert!(spread(&results[1].1) > spread(&results[0].1));
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_player("c").unwrap();
+
+        assert_eq!((&elo).into_iter().count(), 3);
+
+        let mut players = Vec::new();
+        for p in elo {
+            players.push(p);
+        }
+        players.sort();
+
+        let names = players.iter().map(|p| p.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn suspicious_pairs() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        for _ in 0..10 {
+            elo.add_game("b", "a", false).unwrap();
+        }
+        elo.add_game("c", "d", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo.add_game("d", "b", true).unwrap();
+
+        assert_eq!(
+            elo.suspicious_pairs(3, 2.0),
+            [("a".to_string(), "b".to_string())]
+        );
+        assert!(elo.suspicious_pairs(10, 2.0).is_empty());
+    }
+
+    #[test]
+    fn game_vs_rating() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game_vs_rating("a", 1500, false, true).unwrap();
+
+        assert_eq!(elo["a"].rating(), 1030);
+        assert_eq!(elo["a"].number_of_games(), 1);
+
+        elo.add_game_vs_rating("a", 1500, false, false).unwrap();
+        assert_eq!(elo["a"].rating(), 1028);
+
+        let hm = elo.into_storage();
+        assert_eq!(hm.len(), 1);
+    }
+
+    #[test]
+    fn is_calibrated() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+
+        // a keeps beating b, so b's rating is still swinging down
+        for _ in 0..4 {
+            elo.add_game("a", "b", false).unwrap();
+        }
+        // c and d trade draws and stay at the starting rating
+        for _ in 0..4 {
+            elo.add_game("c", "d", true).unwrap();
+        }
+
+        assert_eq!(elo.rating_history("c"), [1000, 1000, 1000, 1000]);
+        assert_eq!(elo.is_calibrated("c", 3, 5), Some(true));
+        assert_eq!(elo.is_calibrated("b", 3, 5), Some(false));
+        assert_eq!(elo.is_calibrated("c", 5, 5), Some(false));
+        assert_eq!(elo.is_calibrated("e", 3, 5), None);
+
+        // the threshold is in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .history(true)
+            .precision_multiplier(10)
+            .build();
+        for i in 0..4 {
+            if i % 2 == 0 {
+                elo.add_game("a", "b", false).unwrap();
+            } else {
+                elo.add_game("b", "a", false).unwrap();
+            }
+        }
+        assert_eq!(elo.is_calibrated("a", 3, 20), Some(true));
+        assert_eq!(elo.is_calibrated("a", 3, 5), Some(false));
+    }
+
+    #[test]
+    fn storage() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo.storage().len(), 2);
+
+        elo.storage_mut().remove("b");
+        assert_eq!(elo.storage().len(), 1);
+        assert!(elo.get_player("b").is_none());
+    }
+
+    #[test]
+    fn series_win_probability() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].rating = 1050;
+
+        let single = elo.series_win_probability("a", "b", 1).unwrap();
+        let bo3 = elo.series_win_probability("a", "b", 3).unwrap();
+        let bo7 = elo.series_win_probability("a", "b", 7).unwrap();
+
+        assert!((single - crate::expected_score(1050, 1000)).abs() < 1e-9);
+        assert!(single < bo3);
+        assert!(bo3 < bo7);
+        assert!((elo.series_win_probability("b", "a", 7).unwrap() + bo7 - 1.0).abs() < 1e-9);
+        assert_eq!(elo.series_win_probability("a", "c", 7), None);
+    }
+
+    #[test]
+    fn recent_results() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .recent_results_window(3)
+            .build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("a", "b", true).unwrap();
+        elo.add_game("a", "b", false).unwrap();
+
+        use GameResult::*;
+        assert_eq!(elo["a"].recent_results(), [Loss, Draw, Win]);
+        assert_eq!(elo["b"].recent_results(), [Win, Draw, Loss]);
+
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        assert!(elo["a"].recent_results().is_empty());
+    }
+
+    #[test]
+    fn remove_and_recompute() {
+        let mut without = EloBuilder::new(HashMap::new()).history(true).build();
+        without.add_game("a", "b", false).unwrap();
+        without.add_game("b", "a", true).unwrap();
+
+        let mut with = EloBuilder::new(HashMap::new()).history(true).build();
+        with.add_game("c", "a", false).unwrap();
+        with.add_game("a", "b", false).unwrap();
+        with.add_game("b", "c", false).unwrap();
+        with.add_game("b", "a", true).unwrap();
+        with["a"].set_deviation(Some(200));
+        with.remove_and_recompute("c").unwrap();
+
+        assert!(with.get_player("c").is_none());
+        assert_eq!(with.history.len(), 2);
+        // the uncertainty of a rating is not recomputed, so it is kept
+        assert_eq!(with["a"].deviation(), Some(200));
+        with["a"].set_deviation(None);
+        assert_eq!(with.into_storage(), without.into_storage());
+
+        // without history there is nothing to replay, so the others keep their ratings
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        let a = elo["a"].clone();
+        elo.remove_and_recompute("c").unwrap();
+        assert!(elo.get_player("c").is_none());
+        assert_eq!(elo["a"], a);
+    }
+
+    #[test]
+    fn expected_clamp() {
+        let play_upset = |mut elo: Elo<HashMap<String, Player>>| {
+            elo.add_player("favorite").unwrap();
+            elo.add_player("underdog").unwrap();
+            elo["favorite"].rating = 2000;
+            elo.add_game("underdog", "favorite", false).unwrap();
+            2000 - elo["favorite"].rating()
+        };
+
+        let unclamped = play_upset(Elo::new(HashMap::new()));
+        let clamped = play_upset(
+            EloBuilder::new(HashMap::new())
+                .expected_clamp(0.1, 0.9)
+                .build(),
+        );
+
+        assert_eq!(unclamped, 32);
+        assert_eq!(clamped, 29);
+    }
+
+    #[test]
+    #[should_panic]
+    fn expected_clamp_invalid() {
+        EloBuilder::new(HashMap::new()).expected_clamp(0.9, 0.1);
+    }
+
+    #[test]
+    fn bradley_terry() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        for (winner, loser, times) in [("a", "b", 3), ("b", "a", 1), ("b", "c", 3), ("c", "b", 1)] {
+            for _ in 0..times {
+                elo.add_game(winner, loser, false).unwrap();
+            }
+        }
+        elo.add_game("a", "c", false).unwrap();
+
+        let strengths = elo.bradley_terry(100);
+        let names = strengths
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!((strengths.iter().map(|(_, s)| s).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn max_players() {
+        let mut elo = EloBuilder::new(HashMap::new()).max_players(2).build();
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+
+        assert_eq!(elo.add_player("c"), Err(EloError::PoolFull(2)));
+        assert_eq!(elo.add_game("a", "c", false), Err(EloError::PoolFull(2)));
+        assert_eq!(elo.add_game("c", "d", false), Err(EloError::PoolFull(2)));
+        assert!(elo.entry("c").or_create().is_err());
+
+        // replacing an existing player is fine
+        elo.add_player("a").unwrap();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo.into_storage().len(), 2);
+    }
+
+    #[test]
+    fn biggest_upsets() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("c", "b", true).unwrap();
+
+        let upsets = elo.biggest_upsets(2);
+        assert_eq!(upsets.len(), 2);
+        assert_eq!(upsets[0].winner(), "b");
+        assert_eq!(upsets[0].loser(), "a");
+        assert!(upsets[0].winner_rating() < upsets[0].loser_rating());
+        assert!(upsets[0].winner_expected() < 0.5);
+        assert!(upsets[0].winner_expected() <= upsets[1].winner_expected());
+    }
+
+    #[test]
+    fn precision_multiplier() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .precision_multiplier(10)
+            .build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+
+        assert_eq!(elo["a"].rating(), 9985);
+        assert_eq!(elo["b"].rating(), 10015);
+        assert_eq!(elo.rating_display("a"), Some(998.5));
+        assert_eq!(elo.rating_display("b"), Some(1001.5));
+
+        // the same games at whole points
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        assert_eq!(elo.rating_display("a"), Some(999.0));
+    }
+
+    #[test]
+    fn projected_rank_changes() {
+        let mut elo = Elo::new(HashMap::new());
+        for (name, rating) in [("a", 1100), ("b", 1090), ("c", 1080), ("d", 1000)] {
+            elo.add_player(name).unwrap();
+            elo[name].rating = rating;
+        }
+
+        let pairings = [("c".to_string(), "d".to_string())];
+        let projected = elo.project_round(&pairings);
+        assert_eq!(projected[1].name(), "c");
+        assert_eq!(elo["c"].rating(), 1080);
+
+        assert_eq!(
+            elo.projected_rank_changes(&pairings),
+            [("c".to_string(), 1), ("d".to_string(), 0)]
+        );
+    }
+
+    #[test]
+    fn win_rate_lower_bound() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("once", "a", false).unwrap();
+        for _ in 0..50 {
+            elo.add_game("often", "b", false).unwrap();
+        }
+
+        assert_eq!(elo["often"].wins(), 50);
+        assert_eq!(elo["b"].losses(), 50);
+
+        let once = elo.win_rate_lower_bound("once", 0.95).unwrap();
+        let often = elo.win_rate_lower_bound("often", 0.95).unwrap();
+        assert!(once < 0.3);
+        assert!(often > 0.9);
+        assert!(elo.win_rate_lower_bound("b", 0.95).unwrap() < 1e-9);
+        for confidence in [0.0, 1.0, 1.5, f64::NAN] {
+            assert_eq!(elo.win_rate_lower_bound("once", confidence), None);
+        }
+        assert_eq!(elo.win_rate_lower_bound("c", 0.95), None);
+    }
+
+    #[test]
+    fn find_duplicates() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("John Doe", "Jane", false).unwrap();
+        elo.add_game("john doe ", "Jane", false).unwrap();
+        elo.add_player("Johnny").unwrap();
+
+        let duplicates = elo.find_duplicates(|name| name.trim().to_lowercase());
+        assert_eq!(duplicates, [["John Doe", "john doe "]]);
+
+        elo.absorb("John Doe", "john doe ").unwrap();
+        assert!(elo.get_player("john doe ").is_none());
+        assert_eq!(elo["John Doe"].number_of_games(), 2);
+        assert_eq!(elo["John Doe"].wins(), 2);
+        // 1016 and 1015 averaged, rounded up
+        assert_eq!(elo["John Doe"].rating(), 1016);
+        assert!(elo
+            .find_duplicates(|name| name.trim().to_lowercase())
+            .is_empty());
+
+        assert_eq!(
+            elo.absorb("John Doe", "nobody"),
+            Err(EloError::PlayerNotFound("nobody".to_string()))
+        );
+
+        // the rest of the record is merged too
+        let day = 24 * 60 * 60;
+        let mut elo = EloBuilder::new(HashMap::new())
+            .milestones(vec![1010])
+            .recent_results_window(5)
+            .build();
+        elo.add_game_at("a", "x", false, day).unwrap();
+        elo.add_game_at("b", "x", false, 2 * day).unwrap();
+        elo.add_game_at("x", "b", false, 3 * day).unwrap();
+        elo["b"].add_label("club");
+        elo.award_bonus("b", 5, "tournament").unwrap();
+        elo.absorb("a", "b").unwrap();
+        assert_eq!(elo.audit_log()[0].player(), "a");
+        assert_eq!(elo["a"].milestones(), [1010]);
+        assert!(elo["a"].labels().contains("club"));
+        assert_eq!(elo["a"].last_played(), Some(3 * day));
+        assert_eq!(elo["a"].longest_win_streak(), 1);
+        assert_eq!(
+            elo["a"].recent_results(),
+            [GameResult::Win, GameResult::Win, GameResult::Loss]
+        );
+    }
+
+    #[test]
+    fn calibration_report() {
+        // reset the ratings before each game so every game has the same prediction
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        for (winner, loser) in [("a", "b"), ("a", "b"), ("b", "a"), ("a", "b")] {
+            elo["a"].rating = 1200;
+            elo["b"].rating = 1000;
+            elo.add_game(winner, loser, false).unwrap();
+        }
+
+        let report = elo.calibration_report(10);
+        assert_eq!(report.len(), 2);
+        for (expected, actual, count) in report {
+            assert_eq!(count, 4);
+            assert!((expected - actual).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn momentum() {
+        // the largest distance from the starting rating while trading wins
+        let alternate = |mut elo: Elo<HashMap<String, Player>>| {
+            (0..10)
+                .map(|i| {
+                    let (winner, loser) = if i % 2 == 0 { ("a", "b") } else { ("b", "a") };
+                    elo.add_game(winner, loser, false).unwrap();
+                    elo["a"].rating().abs_diff(1000)
+                })
+                .max()
+                .unwrap()
+        };
+
+        let undamped = alternate(Elo::new(HashMap::new()));
+        let damped = alternate(EloBuilder::new(HashMap::new()).momentum(0.5).build());
+        assert_eq!(undamped, 16);
+        assert_eq!(damped, 8);
+    }
+
+    #[test]
+    fn points_exchanged() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["b"].rating = 1100;
+
+        let exchanged = elo.points_exchanged("a", "b", false).unwrap();
+        assert_eq!(elo["a"].rating(), 1000);
+
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(exchanged, 20);
+        assert_eq!(elo["a"].rating(), 1000 + exchanged as usize);
+        assert_eq!(elo["b"].rating(), 1100 - exchanged as usize);
+
+        assert!(elo.points_exchanged("a", "a", false).is_err());
+        assert_eq!(
+            elo.points_exchanged("a", "c", false),
+            Err(EloError::PlayerNotFound("c".to_string()))
+        );
+
+        // the exchange is in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .precision_multiplier(10)
+            .build();
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        assert_eq!(elo.points_exchanged("a", "b", false), Ok(16));
+    }
+
+    #[test]
+    fn team_game_score() {
+        let play = |score1, score2| {
+            let mut elo = Elo::new(HashMap::new());
+            elo.add_team_game_score(&["a", "b"], &["c", "d"], score1, score2)
+                .unwrap();
+            elo
+        };
+
+        let narrow = play(3, 1);
+        let clean = play(4, 0);
+        assert_eq!(narrow["a"].rating(), 1008);
+        assert_eq!(narrow["d"].rating(), 992);
+        assert_eq!(clean["b"].rating(), 1016);
+        assert_eq!(clean["c"].rating(), 984);
+        assert_eq!(narrow["a"].wins(), 1);
+        assert_eq!(narrow["c"].losses(), 1);
+
+        let mut elo = Elo::new(HashMap::new());
+        assert_eq!(
+            elo.add_team_game(&["a", "b"], &["b"], false),
+            Err(EloError::SelfPlay("b".to_string()))
+        );
+        assert_eq!(
+            elo.add_team_game(&[], &["b"], false),
+            Err(EloError::EmptyTeam)
+        );
+        assert!(matches!(
+            elo.add_team_game(&["a", "a"], &["b"], false),
+            Err(EloError::InvalidPlayer(_))
+        ));
+        assert!(elo.storage().is_empty());
+
+        // team games count towards the day like any other game
+        elo.add_team_game(&["a", "b"], &["c"], false).unwrap();
+        assert!(elo["a"].last_played().is_some());
+        assert_eq!(elo["c"].games_today, 1);
+
+        // a full pool is found before anyone is added
+        let mut elo = EloBuilder::new(HashMap::new()).max_players(3).build();
+        elo.add_player("a").unwrap();
+        assert_eq!(
+            elo.add_team_game(&["a", "b"], &["c", "d"], false),
+            Err(EloError::PoolFull(3))
+        );
+        assert_eq!(elo.storage().len(), 1);
+    }
+
+    #[test]
+    fn inactive_players() {
+        let day = 24 * 60 * 60;
+        let now = 100 * day;
+
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game_at("old", "older", false, 10 * day).unwrap();
+        elo.add_game_at("older", "b", false, 5 * day).unwrap();
+        elo.add_game_at("a", "b", false, 99 * day).unwrap();
+        elo.add_player("new").unwrap();
+
+        assert_eq!(elo["a"].last_played(), Some(99 * day));
+
+        let inactive = elo.inactive_players(now, 30 * day);
+        let names = inactive.iter().map(|p| p.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["old", "older", "new"]);
+    }
+
+    #[test]
+    fn seed_from_winrates() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.seed_from_winrates(&[("good".to_string(), 0.75), ("bad".to_string(), 0.25)])
+            .unwrap();
+
+        assert_eq!(elo["good"].rating(), 1191);
+        assert_eq!(elo["bad"].rating(), 809);
+        assert_eq!(elo["good"].number_of_games(), 0);
+
+        // a full pool is found before anyone is seeded
+        let mut elo = EloBuilder::new(HashMap::new()).max_players(2).build();
+        elo.add_player("a").unwrap();
+        let records = [("a", 0.75), ("b", 0.25), ("c", 0.5)]
+            .map(|(name, win_rate)| (name.to_string(), win_rate));
+        assert_eq!(elo.seed_from_winrates(&records), Err(EloError::PoolFull(2)));
+        assert_eq!(elo["a"].rating(), 1000);
+        assert_eq!(elo.storage().len(), 1);
+
+        // repeated names only count once
+        let twice = [&records[..2], &records[..2]].concat();
+        elo.seed_from_winrates(&twice).unwrap();
+        assert_eq!(elo.storage().len(), 2);
+    }
+
+    #[test]
+    fn diff() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_player("idle").unwrap();
+        let before = elo.snapshot();
+
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("c", "b", false).unwrap();
+        let after = elo.snapshot();
+
+        let changes = super::diff(&before, &after);
+        let expected = ["a", "b", "c"].map(|name| {
+            let old = before.rating(name).unwrap_or(1000) as i64;
+            (name.to_string(), elo[name].rating() as i64 - old)
+        });
+        assert_eq!(changes, expected);
+        assert_eq!(changes[2], ("c".to_string(), 16));
+
+        // changes are in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .precision_multiplier(10)
+            .build();
+        let before = elo.snapshot();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(
+            super::diff(&before, &elo.snapshot()),
+            [("a".to_string(), 16), ("b".to_string(), -16)]
+        );
+    }
+
+    #[test]
+    fn exact_delta() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+
+        assert_eq!(elo.exact_delta("a", "b", false), (16.0, -16.0));
+        assert_eq!(elo.exact_delta("a", "b", true), (0.0, 0.0));
+
+        elo["b"].rating = 1100;
+        let (gain, loss) = elo.exact_delta("a", "b", false);
+        assert!((gain - 20.48).abs() < 0.01);
+        assert_eq!(gain, -loss);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn from_lichess_json() {
+        let json = r#"[
+            {"username": "magnus", "rating": 2850, "games": 1200},
+            {"username": "me", "rating": 1350, "games": 42}
+        ]"#;
+
+        let elo = Elo::from_lichess_json(json).unwrap();
+        assert_eq!(elo["magnus"].rating(), 2850);
+        assert_eq!(elo["magnus"].number_of_games(), 1200);
+        assert_eq!(elo["me"].rating(), 1350);
+        assert_eq!(elo["me"].number_of_games(), 42);
+
+        assert!(matches!(
+            Elo::from_lichess_json(r#"[{"username": "me"}]"#),
+            Err(EloError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn from_pairwise_counts() {
+        let elo = Elo::from_pairwise_counts(&["a", "b", "c"], &[vec![0, 8], vec![2, 0]]);
+
+        assert!(elo["a"].rating() > elo["b"].rating());
+        assert_eq!(elo["a"].number_of_games(), 10);
+        assert_eq!(elo["c"].rating(), 1000);
+        assert_eq!(elo["c"].number_of_games(), 0);
+    }
+
+    #[test]
+    fn inflation_sink() {
+        // a strong player farms newcomers and leaves, taking their points with them
+        let drift = |mut elo: Elo<HashMap<String, Player>>| {
+            for i in 0..20 {
+                elo.add_game("strong", &format!("new{}", i % 5), false)
+                    .unwrap();
+            }
+            elo.storage_mut().remove("strong");
+
+            for i in 0..20 {
+                elo.add_game(
+                    &format!("new{}", i % 5),
+                    &format!("new{}", (i + 1) % 5),
+                    false,
+                )
+                .unwrap();
+            }
+            (elo.mean_rating().unwrap() - 1000.0).abs()
+        };
+
+        let without = drift(Elo::new(HashMap::new()));
+        let with = drift(EloBuilder::new(HashMap::new()).inflation_sink(0.05).build());
+        assert!(without > 20.0);
+        assert!(with < without - 10.0);
+    }
+
+    #[test]
+    fn last_game() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        assert!(elo.last_game().is_none());
+
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("c", "a", true).unwrap();
+
+        let game = elo.last_game().unwrap();
+        assert_eq!(game.player1(), "c");
+        assert_eq!(game.player2(), "a");
+        assert!(game.is_draw());
+    }
+
+    #[test]
+    fn skill_percent() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        assert_eq!(elo.skill_percent("a"), Some(50.0));
+
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_player("c").unwrap();
+
+        assert_eq!(elo.skill_percent("a"), Some(100.0));
+        assert_eq!(elo.skill_percent("b"), Some(0.0));
+        assert_eq!(elo.skill_percent("c"), Some(50.0));
+        assert_eq!(elo.skill_percent("d"), None);
+    }
+
+    #[test]
+    fn on_established() {
+        let established = Arc::new(Mutex::new(Vec::new()));
+
+        let mut elo = EloBuilder::new(HashMap::new()).provisional_games(3).build();
+        let log = Arc::clone(&established);
+        elo.on_established(Box::new(move |player| {
+            log.lock()
+                .unwrap()
+                .push((player.name().to_string(), player.number_of_games()))
+        }));
+
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "b", false).unwrap();
+        assert!(established.lock().unwrap().is_empty());
+
+        elo.add_game("a", "c", false).unwrap();
+        assert_eq!(*established.lock().unwrap(), [("a".to_string(), 3)]);
+
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(
+            *established.lock().unwrap(),
+            [("a".to_string(), 3), ("b".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn rating_gini() {
+        let mut elo = Elo::new(HashMap::new());
+        assert_eq!(elo.rating_gini(), 0.0);
+
+        for name in ["a", "b", "c", "d"] {
+            elo.add_player(name).unwrap();
+        }
+        assert!(elo.rating_gini().abs() < 1e-9);
+
+        elo["a"].rating = 3000;
+        elo["b"].rating = 100;
+        elo["c"].rating = 100;
+        elo["d"].rating = 100;
+        // the mean absolute difference is 6 * 2900 / 16, over twice the mean of 825
+        assert!((elo.rating_gini() - 2900.0 * 6.0 / 16.0 / 1650.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn replay_weighted() {
+        let play = |mut elo: Elo<HashMap<String, Player>>| {
+            elo.add_game("a", "b", false).unwrap();
+            elo.add_game("b", "a", false).unwrap();
+            elo.replay_weighted().unwrap();
+            elo
+        };
+
+        let unweighted = play(EloBuilder::new(HashMap::new()).history(true).build());
+        assert_eq!(unweighted["a"].rating(), 999);
+        assert_eq!(unweighted["b"].rating(), 1001);
+
+        // the first game only counts for a tenth
+        let weighted = play(
+            EloBuilder::new(HashMap::new())
+                .history(true)
+                .recency_decay(0.1)
+                .build(),
+        );
+        assert_eq!(weighted["a"].rating(), 986);
+        assert_eq!(weighted["b"].rating(), 1014);
+        assert_eq!(weighted.history.len(), 2);
+
+        // without history the ratings are kept rather than reset
+        let unrecorded = play(EloBuilder::new(HashMap::new()).recency_decay(0.1).build());
+        assert_eq!(unrecorded["a"].rating(), 999);
+        assert_eq!(unrecorded["a"].number_of_games(), 2);
+    }
+
+    #[test]
+    fn validate() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "c", true).unwrap();
+        assert_eq!(elo.validate(), Ok(()));
+
+        elo["b"].number_of_games = 1;
+        assert!(elo.validate().is_err());
+
+        elo["b"].number_of_games = 2;
+        elo["c"].wins = 1;
+        assert!(elo.validate().is_err());
+
+        elo["c"].wins = 0;
+        elo["a"].name = "z".to_string();
+        assert!(elo.validate().is_err());
+    }
+
+    #[test]
+    fn thurstone() {
+        let expected = |model| {
+            let mut elo = EloBuilder::new(HashMap::new()).model(model).build();
+            elo.add_player("a").unwrap();
+            elo.add_player("b").unwrap();
+            let even = elo.expected_scores_against("a")[0].1;
+            elo["a"].rating = 1400;
+            (even, elo.expected_scores_against("a")[0].1)
+        };
+
+        let (logistic_even, logistic) = expected(Model::Logistic);
+        let (thurstone_even, thurstone) = expected(Model::Thurstone);
+        assert!((logistic_even - 0.5).abs() < 1e-6);
+        assert!((thurstone_even - 0.5).abs() < 1e-6);
+        assert!((logistic - thurstone).abs() > 0.001);
+        assert!((logistic - thurstone).abs() < 0.05);
+    }
+
+    #[test]
+    fn daily_rated_cap() {
+        let day = SECONDS_PER_DAY;
+        let mut elo = EloBuilder::new(HashMap::new()).daily_rated_cap(2).build();
+
+        elo.add_game_at("a", "b", false, 10 * day).unwrap();
+        elo.add_game_at("a", "b", false, 10 * day + 60).unwrap();
+        let (a, b) = (elo["a"].rating(), elo["b"].rating());
+        assert!(a > 1000);
+
+        elo.add_game_at("a", "b", false, 10 * day + 120).unwrap();
+        elo.add_game_at("b", "a", false, 10 * day + 180).unwrap();
+        assert_eq!(elo["a"].rating(), a);
+        assert_eq!(elo["b"].rating(), b);
+        assert_eq!(elo["a"].number_of_games(), 4);
+
+        // the next day counts again
+        elo.add_game_at("a", "b", false, 11 * day).unwrap();
+        assert!(elo["a"].rating() > a);
+
+        // team and bot games are capped too
+        let mut elo = EloBuilder::new(HashMap::new()).daily_rated_cap(1).build();
+        elo.add_player("coach").unwrap();
+        elo.add_game("a", "b", false).unwrap();
+        let a = elo["a"].rating();
+        elo.add_team_game(&["a"], &["c"], false).unwrap();
+        elo.add_game_vs_rating("a", 1000, false, true).unwrap();
+        elo.add_one_sided_game("a", "coach", false, true).unwrap();
+        assert_eq!(elo["a"].rating(), a);
+        assert_eq!(elo["a"].number_of_games(), 4);
+        assert!(elo["c"].rating() < 1000);
+    }
+
+    #[test]
+    fn rating_zscore() {
+        let mut elo = Elo::new(HashMap::new());
+        for (name, rating) in [("a", 1200), ("b", 1000), ("c", 800)] {
+            elo.add_player(name).unwrap();
+            elo[name].rating = rating;
+        }
+
+        let std_dev = elo.rating_std_dev().unwrap();
+        assert!((std_dev - (80_000.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!(elo.rating_zscore("a").unwrap() > 0.0);
+        assert!(elo.rating_zscore("b").unwrap().abs() < 1e-9);
+        assert!(elo.rating_zscore("c").unwrap() < 0.0);
+        assert_eq!(elo.rating_zscore("d"), None);
+    }
+
+    #[test]
+    fn add_bye() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        let rating = elo["a"].rating();
+
+        elo.add_bye("a", 0.5).unwrap();
+        elo.add_bye("a", 1.0).unwrap();
+        assert_eq!(elo["a"].rating(), rating);
+        assert_eq!(elo["a"].number_of_games(), 3);
+        assert_eq!(elo["a"].wins(), 2);
+        assert_eq!(elo["a"].draws(), 1);
+
+        elo.add_bye("c", 0.5).unwrap();
+        assert_eq!(elo["c"].rating(), 1000);
+        assert_eq!(elo["c"].number_of_games(), 1);
+
+        // byes and games against a rating count as playing
+        elo.add_game_vs_rating("d", 1000, false, true).unwrap();
+        assert!(elo["c"].last_played().is_some());
+        assert!(elo["d"].last_played().is_some());
+        assert!(elo.inactive_players(now(), 60 * 60).is_empty());
+    }
+
+    #[test]
+    fn milestones() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .milestones(vec![1100, 1200, 1500])
+            .build();
+
+        for i in 0..20 {
+            elo.add_game("a", &format!("opponent{}", i), false).unwrap();
+        }
+        assert!(elo["a"].rating() > 1200 && elo["a"].rating() < 1500);
+
+        // dropping below a milestone and climbing back does not count it twice
+        elo["a"].rating = 1000;
+        elo.add_game("a", "b", false).unwrap();
+        elo["a"].rating = 1199;
+        elo.add_game("a", "b", false).unwrap();
+
+        assert_eq!(elo["a"].milestones(), &[1100, 1200]);
+        assert!(elo["opponent0"].milestones().is_empty());
+    }
+
+    #[test]
+    fn optimal_k() {
+        let game = |winner: &str, loser: &str| (winner.to_string(), loser.to_string(), false);
+
+        // a always wins, so the faster the ratings move the better the predictions
+        let dominant = vec![game("a", "b"); 20];
+        assert_eq!(super::optimal_k(&dominant, &[4.0, 16.0, 64.0]), 64.0);
+
+        // evenly matched players with alternating results are best predicted by not moving
+        let even = (0..20)
+            .map(|i| {
+                if i % 2 == 0 {
+                    game("a", "b")
+                } else {
+                    game("b", "a")
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(super::optimal_k(&even, &[32.0, 0.0, 16.0]), 0.0);
+    }
+
+    #[test]
+    fn lock() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        let before = elo.leaderboard().into_iter().cloned().collect::<Vec<_>>();
+
+        elo.lock();
+        assert_eq!(elo.add_game("a", "b", false), Err(EloError::PoolLocked));
+        assert_eq!(elo.add_game("a", "c", false), Err(EloError::PoolLocked));
+        assert_eq!(
+            elo.add_team_game(&["a"], &["b"], false),
+            Err(EloError::PoolLocked)
+        );
+
+        // changes outside of games are frozen too
+        let locked = Err(EloError::PoolLocked);
+        assert_eq!(elo.add_player("c"), locked);
+        assert_eq!(elo.award_bonus("a", 10, "tournament"), locked);
+        assert_eq!(elo.set_k_multiplier("a", 2.0), locked);
+        assert_eq!(elo.absorb("a", "b"), locked);
+        let renames = HashMap::from([("a".to_string(), "z".to_string())]);
+        assert_eq!(elo.rename_all(&renames), locked);
+        assert_eq!(elo.seed_from_winrates(&[("a".to_string(), 0.9)]), locked);
+        assert_eq!(elo.decay_returning_player("a", 6, 100), locked);
+        assert_eq!(elo.apply_period_decay(0, 3), locked);
+        assert_eq!(elo.remove_and_recompute("b"), locked);
+        assert_eq!(elo.replay_weighted(), locked);
+        assert_eq!(elo.merge_case_duplicates(), locked);
+        assert_eq!(
+            elo.recompute_with_config(EloBuilder::new(HashMap::new())),
+            locked
+        );
+        assert_eq!(
+            elo.leaderboard().into_iter().cloned().collect::<Vec<_>>(),
+            before
+        );
+        assert!(elo.audit_log().is_empty());
+
+        elo.unlock();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo["a"].number_of_games(), 2);
+    }
+
+    #[test]
+    fn avg_rating_change() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("c", "d", false).unwrap();
+        // both are at 1016, so the draw changes nothing
+        elo.add_game("a", "c", true).unwrap();
+
+        assert_eq!(elo.avg_rating_change("a"), Some(8.0));
+        assert_eq!(elo.avg_rating_change("b"), Some(16.0));
+        assert_eq!(elo.avg_rating_change("e"), None);
+
+        // the change is in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .history(true)
+            .precision_multiplier(10)
+            .build();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo.avg_rating_change("a"), Some(16.0));
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .k_factor(24.0)
+            .case_insensitive(true)
+            .build();
+        elo.add_player("Alice").unwrap();
+        elo.add_game("alice", "bob", false).unwrap();
+        elo.add_game("carol", "bob", true).unwrap();
+
+        let bytes = elo.to_bincode();
+        let loaded = Elo::from_bincode(&bytes).unwrap();
+        assert_eq!(loaded.config(), elo.config());
+        assert_eq!(loaded.leaderboard(), elo.leaderboard());
+        assert_eq!(loaded["ALICE"].display_name(), "Alice");
+
+        assert!(matches!(
+            Elo::from_bincode(&bytes[..3]),
+            Err(EloError::ParseError(_))
+        ));
+
+        let config = EloConfig {
+            precision_multiplier: 0,
+            ..EloConfig::default()
+        };
+        let bytes = bincode::serialize(&(config, Vec::<Player>::new())).unwrap();
+        let mut loaded = Elo::from_bincode(&bytes).unwrap();
+        assert_eq!(loaded.config().precision_multiplier(), 1);
+        loaded.add_game("a", "b", false).unwrap();
+        assert_eq!(loaded.rating_display("a"), Some(1016.0));
+    }
+
+    #[test]
+    fn recompute_with_config() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        for _ in 0..5 {
+            elo.add_game("a", "b", false).unwrap();
         }
+        elo.add_player("c").unwrap();
+        let spread = elo["a"].rating() - elo["b"].rating();
+
+        elo.recompute_with_config(EloBuilder::new(HashMap::new()).k_factor(16.0))
+            .unwrap();
+        assert_eq!(elo.config().k_factor(), 16.0);
+        assert!(elo.config().history());
+        assert!(elo["a"].rating() - elo["b"].rating() < spread);
+        assert_eq!(elo["a"].number_of_games(), 5);
+        assert_eq!(elo["c"].rating(), 1000);
+        assert_eq!(elo.rating_history("a").len(), 5);
+
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.recompute_with_config(EloBuilder::new(HashMap::new()).k_factor(16.0))
+            .unwrap();
+        assert_eq!(elo.config().k_factor(), 32.0);
+        assert_eq!(elo["a"].rating(), 1016);
+        assert_eq!(elo["a"].number_of_games(), 1);
+
+        // games the new config would reject stop the recompute
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "b", true).unwrap();
+        let a = elo["a"].clone();
+        assert_eq!(
+            elo.recompute_with_config(EloBuilder::new(HashMap::new()).allow_draws(false)),
+            Err(EloError::DrawsNotAllowed)
+        );
+        assert!(elo.config().allow_draws());
+        assert_eq!(elo.history.len(), 2);
+        assert_eq!(elo["a"], a);
     }
 
-    #[allow(dead_code)]
-    pub fn add_player<TS: ToString>(&mut self, name: TS) {
-        self.players.add_player(Player {
-            name: name.to_string(),
-            rating: self.starting_elo,
-            number_of_games: 0,
-        });
+    #[test]
+    fn games_between() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("a", "b", true).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+
+        assert_eq!(elo.games_between("a", "b"), 3);
+        assert_eq!(elo.games_between("b", "a"), 3);
+        assert_eq!(elo.games_between("a", "c"), 1);
+        assert_eq!(elo.games_between("b", "c"), 0);
     }
 
-    #[allow(dead_code)]
-    pub fn try_add(&mut self, name: &str) {
-        if self.players.get(name).is_none() {
-            self.add_player(name);
+    #[test]
+    fn award_bonus() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        let rating = elo["a"].rating();
+
+        elo.award_bonus("a", 25, "won the spring cup").unwrap();
+        assert_eq!(elo["a"].rating(), rating + 25);
+        assert_eq!(elo["a"].number_of_games(), 1);
+
+        let entry = &elo.audit_log()[0];
+        assert_eq!(entry.player(), "a");
+        assert_eq!(entry.points(), 25);
+        assert_eq!(entry.reason(), "won the spring cup");
+
+        assert_eq!(
+            elo.award_bonus("c", 25, "not playing"),
+            Err(EloError::PlayerNotFound("c".to_string()))
+        );
+        assert_eq!(elo.audit_log().len(), 1);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn simulate_season() {
+        use rand::SeedableRng;
+
+        let mut elo = Elo::new(HashMap::new());
+        for name in ["a", "b", "c"] {
+            elo.add_player(name).unwrap();
         }
+        elo["a"].rating = 1600;
+
+        let pairing = |p1: &str, p2: &str| (p1.to_string(), p2.to_string());
+        let remaining = [
+            pairing("a", "b"),
+            pairing("a", "c"),
+            pairing("b", "c"),
+            pairing("b", "a"),
+            pairing("c", "a"),
+            pairing("c", "b"),
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let titles = elo.simulate_season(&remaining, 2000, &mut rng);
+
+        assert!(titles["a"] > 0.8);
+        assert!(titles["b"] > 0.0 && titles["b"] < 0.2);
+        assert!((titles.values().sum::<f64>() - 1.0).abs() < 1e-9);
     }
 
-    /// If is_draw is true, the game is a draw.
-    /// If is_draw is false, the game is won by the first player.
-    #[allow(dead_code)]
-    pub fn add_game(&mut self, player1: &str, player2: &str, is_draw: bool) -> Result<(), String> {
-        if player1 == player2 {
-            return Err(format!(
-                "{} can't play against themselves (you friendless loser)",
-                player1
+    #[test]
+    fn merge_case_duplicates() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("Bob", "carol", false).unwrap();
+        elo.add_game("Bob", "carol", false).unwrap();
+        elo.add_game("bob", "carol", false).unwrap();
+        elo.add_game("Carol", "dave", true).unwrap();
+
+        elo.merge_case_duplicates().unwrap();
+        assert!(elo.get_player("bob").is_none());
+        assert!(elo.get_player("Carol").is_none());
+        assert_eq!(elo["Bob"].number_of_games(), 3);
+        assert_eq!(elo["Bob"].wins(), 3);
+        assert_eq!(elo["carol"].number_of_games(), 4);
+        assert_eq!(elo.storage().len(), 3);
+    }
+
+    #[test]
+    fn rating_at_game() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("c", "d", false).unwrap();
+        elo.add_game("b", "a", false).unwrap();
+        elo.add_game("a", "c", true).unwrap();
+
+        let history = elo.rating_history("a");
+        assert_eq!(elo.rating_at_game("a", 0), Some(1016));
+        assert_eq!(elo.rating_at_game("a", 2), Some(history[2]));
+        assert_eq!(elo.rating_at_game("a", 2), Some(elo["a"].rating()));
+        assert_eq!(elo.rating_at_game("a", 3), None);
+        assert_eq!(elo.rating_at_game("e", 0), None);
+    }
+
+    #[test]
+    fn apply_period_decay() {
+        let decayed = |periods| {
+            let mut elo = EloBuilder::new(HashMap::new()).period_decay(0.1).build();
+            for name in ["a", "b", "c", "d"] {
+                elo.add_player(name).unwrap();
+            }
+            elo["d"].rating = 1400;
+            // a and b just played, so they are active
+            let day = 24 * 60 * 60;
+            elo.add_game_at("a", "b", true, 99 * day).unwrap();
+
+            elo.apply_period_decay(100 * day, periods).unwrap();
+            assert_eq!(elo["a"].rating(), 1000);
+            elo["d"].rating()
+        };
+
+        // the mean is 1100, so d starts 300 above it
+        assert_eq!(decayed(1), 1370);
+        assert_eq!(decayed(3), 1319);
+        assert!(1400 - decayed(3) > 2 * (1400 - decayed(1)));
+
+        // a tracked deviation is widened instead
+        let mut elo = EloBuilder::new(HashMap::new()).period_decay(0.1).build();
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].rating = 1400;
+        elo["a"].set_deviation(Some(50));
+        elo.apply_period_decay(0, 3).unwrap();
+        assert_eq!(elo["a"].rating(), 1400);
+        assert_eq!(elo["a"].deviation(), Some(79));
+        assert!(elo["b"].rating() > 1000);
+
+        elo.apply_period_decay(0, 1000).unwrap();
+        assert_eq!(elo["a"].deviation(), Some(350));
+    }
+
+    #[test]
+    fn games() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("b", "c", true).unwrap();
+        elo.add_game("c", "a", false).unwrap();
+
+        let games = elo
+            .games()
+            .map(|game| (game.player1(), game.player2(), game.is_draw()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            games,
+            [("a", "b", false), ("b", "c", true), ("c", "a", false)]
+        );
+    }
+
+    #[test]
+    fn confident_leaderboard() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.entry("new").or_create().unwrap().rating = 1300;
+        elo["new"].number_of_games = 1;
+        elo.entry("veteran").or_create().unwrap().rating = 1250;
+        elo["veteran"].number_of_games = 100;
+        elo.add_player("other").unwrap();
+
+        let names = |players: Vec<&Player>| {
+            players
+                .into_iter()
+                .map(|p| p.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(elo.leaderboard()), ["new", "veteran", "other"]);
+        assert_eq!(
+            names(elo.confident_leaderboard()),
+            ["veteran", "new", "other"]
+        );
+    }
+
+    #[test]
+    fn match_log() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        let mut changes = Vec::new();
+        for (p1, p2, is_draw) in [("a", "b", false), ("b", "a", false), ("a", "c", true)] {
+            let before = (
+                elo.entry(p1).or_create().unwrap().rating() as i64,
+                elo.entry(p2).or_create().unwrap().rating() as i64,
+            );
+            elo.add_game(p1, p2, is_draw).unwrap();
+            changes.push((
+                p1,
+                p2,
+                elo[p1].rating() as i64 - before.0,
+                elo[p2].rating() as i64 - before.1,
             ));
         }
 
-        self.try_add(player1);
-        self.try_add(player2);
+        assert_eq!(elo.match_log(), changes);
+        assert_eq!(elo.match_log()[0], ("a", "b", 16, -16));
+
+        // changes are in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .history(true)
+            .precision_multiplier(10)
+            .build();
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo.match_log(), [("a", "b", 16, -16)]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let players = vec![
+            Player::new("a".to_string(), 1200, 10),
+            Player::new("b".to_string(), 1100, 5),
+            Player::new("c".to_string(), 900, 0),
+        ];
+        let elo: Elo<_> = players.clone().into_iter().collect();
+
+        assert_eq!(
+            elo.leaderboard().into_iter().cloned().collect::<Vec<_>>(),
+            players
+        );
+        assert_eq!(elo["b"].number_of_games(), 5);
+    }
+
+    #[test]
+    fn expected_margin() {
+        let mut elo = Elo::new(HashMap::new());
+        for (name, rating) in [("a", 1400), ("b", 1100), ("c", 1000)] {
+            elo.add_player(name).unwrap();
+            elo[name].rating = rating;
+        }
+
+        let big = elo.expected_margin("a", "c", 0.05).unwrap();
+        let small = elo.expected_margin("b", "c", 0.05).unwrap();
+        assert!(big > small && small > 0.0);
+        assert!((big - 20.0).abs() < 1e-9);
+        assert_eq!(elo.expected_margin("c", "a", 0.05), Some(-big));
+        assert_eq!(elo.expected_margin("a", "d", 0.05), None);
+    }
+
+    #[test]
+    fn add_game_returning() {
+        let mut elo = Elo::new(HashMap::new());
+        let (a, b) = elo.add_game_returning("a", "b", false).unwrap();
+
+        assert_eq!((a.rating(), b.rating()), (1016, 984));
+        assert_eq!((a.number_of_games(), b.number_of_games()), (1, 1));
+        assert_eq!(&a, &elo["a"]);
+        assert_eq!(
+            elo.add_game_returning("a", "a", false),
+            Err(EloError::SelfPlay("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn games_to_overtake() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("me").unwrap();
+        elo.add_player("rival").unwrap();
+        elo["rival"].rating = 1050;
+
+        // every win is worth 16 points
+        assert_eq!(elo.games_to_overtake("me", "rival", 1.0), Some(4));
+        let slower = elo.games_to_overtake("me", "rival", 0.75).unwrap();
+        assert!(slower > 4 && slower < 20);
+        assert_eq!(elo.games_to_overtake("me", "rival", 0.5), None);
+        assert_eq!(elo.games_to_overtake("rival", "me", 0.0), Some(0));
+        assert_eq!(elo.games_to_overtake("me", "nobody", 1.0), None);
+    }
+
+    #[test]
+    fn players_with_label() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_player("c").unwrap();
+        elo["a"].add_label("east");
+        elo["c"].add_label("east");
+        elo["b"].add_label("west");
+
+        let east = elo
+            .players_with_label("east")
+            .into_iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>();
+        assert_eq!(east, ["a", "c"]);
+        assert!(elo.players_with_label("north").is_empty());
+
+        // labels are not lost when recomputing
+        elo.recompute_with_config(EloBuilder::new(HashMap::new()))
+            .unwrap();
+        assert_eq!(elo.players_with_label("east").len(), 2);
+    }
+
+    #[test]
+    fn histogram() {
+        let mut elo = Elo::new(HashMap::new());
+        for (name, rating) in [
+            ("a", 950),
+            ("b", 1000),
+            ("c", 1099),
+            ("d", 1250),
+            ("e", 1000),
+        ] {
+            elo.add_player(name).unwrap();
+            elo[name].rating = rating;
+        }
+
+        let histogram = elo.histogram(100);
+        assert_eq!(histogram, [(900, 1), (1000, 3), (1100, 0), (1200, 1)]);
+        assert_eq!(histogram.iter().map(|(_, count)| count).sum::<usize>(), 5);
+        assert!(Elo::new(HashMap::new()).histogram(100).is_empty());
+    }
+
+    #[test]
+    fn expected_score_uncertain() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo["a"].rating = 1200;
+
+        let certain = elo.expected_score_uncertain("a", "b").unwrap();
+        assert!((certain - crate::expected_score(1200, 1000)).abs() < 1e-9);
+
+        elo["a"].set_deviation(Some(100));
+        let uncertain = elo.expected_score_uncertain("a", "b").unwrap();
+        elo["a"].set_deviation(Some(350));
+        let very_uncertain = elo.expected_score_uncertain("a", "b").unwrap();
+        assert!(certain > uncertain && uncertain > very_uncertain && very_uncertain > 0.5);
+        assert_eq!(elo.expected_score_uncertain("a", "c"), None);
+    }
 
-        let (wr, lr) = crate::update_rating(&self[player1], &self[player2], is_draw);
+    #[test]
+    fn rename_all() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("a", "c", false).unwrap();
+        elo.award_bonus("a", 10, "tournament").unwrap();
+        let (a, b) = (elo["a"].clone(), elo["b"].clone());
 
-        let mut update_rating = |player, new_rating: usize| {
-            let p = self.players.get_mut(player).unwrap();
-            p.rating = new_rating;
-            p.number_of_games += 1;
+        let mapping = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect::<HashMap<_, _>>()
         };
+        elo.rename_all(&mapping(&[("a", "b"), ("b", "a")])).unwrap();
+        assert_eq!(elo["b"].rating(), a.rating());
+        assert_eq!(elo["b"].number_of_games(), 2);
+        assert_eq!(elo["a"].rating(), b.rating());
+        assert_eq!(elo.head_to_head("b", "a"), (1, 0, 0));
+        assert_eq!(elo.storage().len(), 3);
+        assert_eq!(elo.audit_log()[0].player(), "b");
 
-        update_rating(player1, wr);
-        update_rating(player2, lr);
+        // collisions are found before anything is renamed
+        assert!(matches!(
+            elo.rename_all(&mapping(&[("a", "d"), ("b", "c")])),
+            Err(EloError::InvalidPlayer(_))
+        ));
+        assert!(matches!(
+            elo.rename_all(&mapping(&[("a", "d"), ("b", "d")])),
+            Err(EloError::InvalidPlayer(_))
+        ));
+        assert_eq!(
+            elo.rename_all(&mapping(&[("a", "d"), ("e", "f")])),
+            Err(EloError::PlayerNotFound("e".to_string()))
+        );
+        assert!(elo.get_player("d").is_none());
+        assert_eq!(elo["b"].rating(), a.rating());
 
-        Ok(())
+        // two keys naming the same player are rejected before anything is renamed
+        let mut elo = EloBuilder::new(HashMap::new())
+            .case_insensitive(true)
+            .build();
+        elo.add_player("Alice").unwrap();
+        assert!(matches!(
+            elo.rename_all(&mapping(&[("Alice", "x"), ("alice", "y")])),
+            Err(EloError::InvalidPlayer(_))
+        ));
+        assert!(elo.get_player("alice").is_some());
     }
 
-    #[allow(dead_code)]
-    pub fn get_player(&self, name: &str) -> Option<&Player> {
-        self.players.get(name)
+    #[test]
+    fn distinct_opponents() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        for _ in 0..3 {
+            elo.add_game("a", "b", false).unwrap();
+        }
+        elo.add_game("c", "a", false).unwrap();
+        elo.add_game("a", "d", true).unwrap();
+
+        assert_eq!(elo.distinct_opponents("a"), 3);
+        assert_eq!(elo.distinct_opponents("b"), 1);
+        assert_eq!(elo.distinct_opponents("e"), 0);
     }
 
-    #[allow(dead_code)]
-    pub fn into_storage(self) -> S {
-        self.players
+    #[test]
+    fn add_one_sided_game() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("coach").unwrap();
+        elo["coach"].rating = 1200;
+
+        elo.add_one_sided_game("learner", "coach", false, true)
+            .unwrap();
+        assert!(elo["learner"].rating() > 1016);
+        assert_eq!(elo["learner"].number_of_games(), 1);
+        assert_eq!(elo["coach"].rating(), 1200);
+        assert_eq!(elo["coach"].number_of_games(), 0);
+
+        // losing to the coach still leaves the coach untouched
+        let before = elo["learner"].rating();
+        elo.add_one_sided_game("learner", "coach", false, false)
+            .unwrap();
+        assert!(elo["learner"].rating() < before);
+        assert_eq!(elo["learner"].number_of_games(), 2);
+        assert_eq!(elo["learner"].losses(), 1);
+        assert_eq!(elo["coach"].rating(), 1200);
+
+        assert_eq!(
+            elo.add_one_sided_game("learner", "nobody", true, false),
+            Err(EloError::PlayerNotFound("nobody".to_string()))
+        );
+        assert_eq!(
+            elo.add_one_sided_game("coach", "coach", true, false),
+            Err(EloError::SelfPlay("coach".to_string()))
+        );
     }
-}
 
-impl<S: EloStorage> Index<&str> for Elo<S> {
-    type Output = Player;
+    #[test]
+    fn longest_win_streak() {
+        let mut elo = Elo::new(HashMap::new());
+        let games = [
+            ("a", "b", false),
+            ("a", "b", false),
+            ("b", "a", false),
+            ("a", "b", false),
+            ("a", "b", false),
+            ("a", "b", false),
+            ("a", "b", true),
+            ("a", "b", false),
+        ];
+        for (p1, p2, is_draw) in games {
+            elo.add_game(p1, p2, is_draw).unwrap();
+        }
 
-    fn index(&self, name: &str) -> &Player {
-        self.players.get(name).unwrap()
+        assert_eq!(elo["a"].longest_win_streak(), 3);
+        assert_eq!(elo["a"].win_streak(), 1);
+        assert_eq!(elo["b"].longest_win_streak(), 1);
     }
-}
 
-impl<S: EloStorage> IndexMut<&str> for Elo<S> {
-    fn index_mut(&mut self, name: &str) -> &mut Player {
-        self.players.get_mut(name).unwrap()
+    #[test]
+    fn partition() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .history(true)
+            .k_factor(20.0)
+            .build();
+        elo.add_game("a", "b", false).unwrap();
+        elo.add_game("c", "d", true).unwrap();
+        let config = elo.config().clone();
+
+        let (strong, rest) = elo.partition(|p| p.rating() > 1000);
+        assert_eq!(strong.storage().len(), 1);
+        assert!(strong.get_player("a").is_some());
+        assert_eq!(rest.storage().len(), 3);
+        assert!(rest.get_player("a").is_none());
+        assert_eq!(strong.config(), &config);
+        assert_eq!(rest.config(), &config);
+
+        assert_eq!(strong.games().count(), 0);
+        assert_eq!(rest.games().count(), 1);
     }
-}
 
-impl EloStorage for HashMap<String, Player> {
-    fn add_player(&mut self, player: Player) {
-        self.insert(player.name.clone(), player);
+    #[test]
+    fn projected_points() {
+        let mut elo = Elo::new(HashMap::new());
+        for name in ["a", "b", "c"] {
+            elo.add_player(name).unwrap();
+        }
+        elo["a"].rating = 1400;
+
+        let pairing = |p1: &str, p2: &str| (p1.to_string(), p2.to_string());
+        let schedule = [pairing("a", "b"), pairing("b", "c"), pairing("c", "a")];
+        let points = elo.projected_points(&schedule, 3.0, 1.0);
+
+        assert!(points["a"] > points["b"]);
+        assert!((points["b"] - points["c"]).abs() < 1e-9);
+        // without draws so far, the points are the expected scores times the points for a win
+        assert!((points.values().sum::<f64>() - 9.0).abs() < 1e-9);
+
+        // once games have been drawn, some of the expected score comes from draws
+        elo.add_game("b", "c", true).unwrap();
+        let points = elo.projected_points(&schedule, 3.0, 1.0);
+        assert!(points.values().sum::<f64>() < 9.0);
     }
 
-    fn update_player(&mut self, player: &Player) {
-        self.insert(player.name().to_string(), player.clone());
+    #[test]
+    fn provisional_cap() {
+        let builder = || {
+            EloBuilder::new(HashMap::new())
+                .provisional_games(5)
+                .provisional_k_factor(120.0)
+        };
+
+        let mut uncapped = builder().build();
+        uncapped.add_game("a", "b", false).unwrap();
+        assert_eq!(uncapped["a"].rating(), 1060);
+
+        let mut capped = builder().provisional_cap(40).build();
+        capped.add_game("a", "b", false).unwrap();
+        assert_eq!(capped["a"].rating(), 1040);
+        assert_eq!(capped["b"].rating(), 960);
+
+        // established players use the normal K-factor, without a cap
+        for name in ["c", "d"] {
+            capped.add_player(name).unwrap();
+            capped[name].number_of_games = 5;
+        }
+        capped.add_game("c", "d", false).unwrap();
+        assert_eq!(capped["c"].rating(), 1016);
     }
 
-    fn get(&self, name: &str) -> Option<&Player> {
-        self.get(name)
+    #[test]
+    fn rating_timeline() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game_at("a", "b", false, 100).unwrap();
+        elo.add_game_at("c", "d", false, 200).unwrap();
+        elo.add_game_at("b", "a", false, 300).unwrap();
+
+        let timeline = elo.rating_timeline("a");
+        assert_eq!(timeline, [(100, 1016), (300, elo["a"].rating())]);
+        assert_eq!(
+            timeline
+                .iter()
+                .map(|&(_, rating)| rating)
+                .collect::<Vec<_>>(),
+            elo.rating_history("a")
+        );
+
+        // replays keep the times the games were played
+        elo.remove_and_recompute("c").unwrap();
+        assert_eq!(elo.rating_timeline("a"), timeline);
+        assert_eq!(elo["a"].last_played(), Some(300));
     }
 
-    fn get_mut(&mut self, name: &str) -> Option<&mut Player> {
-        self.get_mut(name)
+    #[test]
+    fn chaos_index() {
+        let play = |favorite_wins: bool| {
+            let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+            for name in ["strong", "weak"] {
+                elo.add_player(name).unwrap();
+            }
+            elo["strong"].rating = 1600;
+            for _ in 0..5 {
+                if favorite_wins {
+                    elo.add_game("strong", "weak", false).unwrap();
+                } else {
+                    elo.add_game("weak", "strong", false).unwrap();
+                }
+            }
+            elo.chaos_index().unwrap()
+        };
+
+        assert!(play(true) < 0.1);
+        assert!(play(false) > 0.8);
+        assert_eq!(Elo::new(HashMap::new()).chaos_index(), None);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn k_multiplier() {
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo.set_k_multiplier("a", 2.0).unwrap();
+
+        elo.add_game("a", "b", false).unwrap();
+        assert_eq!(elo["a"].rating(), 1032);
+        assert_eq!(elo["b"].rating(), 984);
+        assert_eq!(
+            elo.set_k_multiplier("c", 2.0),
+            Err(EloError::PlayerNotFound("c".to_string()))
+        );
+
+        for multiplier in [f64::NAN, f64::INFINITY, 0.0, -1.0] {
+            assert!(matches!(
+                elo.set_k_multiplier("a", multiplier),
+                Err(EloError::InvalidPlayer(_))
+            ));
+        }
+        assert_eq!(elo["a"].k_multiplier, 2.0);
+    }
 
     #[test]
-    fn single_no_friends() {
+    fn rating_to_beat() {
         let mut elo = Elo::new(HashMap::new());
-        elo.add_player("a");
+        elo.add_player("x").unwrap();
+        elo["x"].rating = 1500;
 
-        assert!(elo.add_game("a", "a", false).is_err());
+        assert_eq!(elo.rating_to_favor("x"), Some(1500));
+        assert!(elo.rating_to_beat("x", 0.75).unwrap().abs_diff(1690) <= 2);
+        assert!(elo.rating_to_beat("x", 0.25).unwrap() < 1500);
+        assert_eq!(elo.rating_to_beat("x", 1.0), None);
+        assert_eq!(elo.rating_to_favor("y"), None);
     }
 
     #[test]
-    fn dual() {
+    fn validate_games() {
+        let game = |p1: &str, p2: &str| (p1.to_string(), p2.to_string(), false);
+        let games = [
+            game("a", "b"),
+            game("a", "a"),
+            game("c", "b"),
+            game("b", "b"),
+        ];
+
+        let mut elo = Elo::new(HashMap::new());
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        assert_eq!(
+            elo.validate_games(&games),
+            [
+                (1, EloError::SelfPlay("a".to_string())),
+                (3, EloError::SelfPlay("b".to_string()))
+            ]
+        );
+        assert!(elo.get_player("c").is_none());
+
+        let mut strict = EloBuilder::new(HashMap::new()).strict(true).build();
+        strict.add_player("a").unwrap();
+        strict.add_player("b").unwrap();
+        let problems = strict.validate_games(&games);
+        assert_eq!(
+            problems.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(problems[1].1, EloError::PlayerNotFound("c".to_string()));
+        assert_eq!(
+            strict.add_game("c", "b", false),
+            Err(EloError::PlayerNotFound("c".to_string()))
+        );
+
+        // every kind of game is strict
+        let unknown = Err(EloError::PlayerNotFound("c".to_string()));
+        assert_eq!(strict.add_team_game(&["a"], &["b", "c"], false), unknown);
+        assert_eq!(strict.add_game_vs_rating("c", 1000, false, true), unknown);
+        assert_eq!(strict.add_one_sided_game("c", "a", false, true), unknown);
+        assert_eq!(strict.add_bye("c", 1.0), unknown);
+        assert!(strict.get_player("c").is_none());
+        assert_eq!(strict["b"].number_of_games(), 0);
+    }
+
+    #[test]
+    fn expected_ranks() {
         let mut elo = Elo::new(HashMap::new());
-        elo.add_player("a");
-        elo.add_player("b");
+        for name in ["a", "b", "c", "d"] {
+            elo.add_player(name).unwrap();
+        }
+        let skills = [("a", 1200.0), ("b", 1500.0), ("c", 1000.0)]
+            .into_iter()
+            .map(|(name, skill)| (name.to_string(), skill))
+            .collect::<HashMap<_, _>>();
+
+        let ranks = elo.expected_ranks(&skills);
+        let names = ranks
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["b", "a", "c"]);
+        assert!(ranks[0].1 > 1.0 && ranks[0].1 < 1.5);
+        // the expected ranks of a field always add up to 1 + 2 + 3
+        assert!((ranks.iter().map(|(_, rank)| rank).sum::<f64>() - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rating_ceiling() {
+        let mut elo = EloBuilder::new(HashMap::new()).rating_ceiling(1050).build();
+        for i in 0..5 {
+            elo.add_game("a", &format!("opponent{}", i), false).unwrap();
+        }
+
+        assert_eq!(elo["a"].rating(), 1050);
+        assert_eq!(elo["a"].number_of_games(), 5);
+    }
+
+    #[test]
+    fn ranking_correlation() {
+        let table = |ratings: &[(&str, usize)]| {
+            let mut elo = Elo::new(HashMap::new());
+            for &(name, rating) in ratings {
+                elo.add_player(name).unwrap();
+                elo[name].rating = rating;
+            }
+            elo
+        };
+        let a = table(&[("a", 1300), ("b", 1200), ("c", 1100), ("d", 1000)]);
+        let same = table(&[
+            ("a", 1600),
+            ("b", 1500),
+            ("c", 900),
+            ("d", 800),
+            ("e", 2000),
+        ]);
+        let swapped = table(&[("a", 1300), ("b", 1050), ("c", 1100), ("d", 1000)]);
+
+        assert!((super::ranking_correlation(&a, &same) - 1.0).abs() < 1e-9);
+        assert!((super::ranking_correlation(&a, &swapped) - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ratings_for_window() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        elo.add_game_at("a", "b", false, 100).unwrap();
+        elo.add_game_at("a", "c", false, 150).unwrap();
+        elo.add_game_at("b", "a", false, 1000).unwrap();
+        elo.add_game_at("b", "c", false, 1100).unwrap();
+
+        let season = elo.ratings_for_window(1000, 2000);
+        let ratings = season
+            .iter()
+            .map(|p| (p.name(), p.rating(), p.number_of_games()))
+            .collect::<Vec<_>>();
+        assert_eq!(ratings, [("b", 1031, 2), ("c", 985, 1), ("a", 984, 1)]);
+
+        assert_eq!(elo.ratings_for_window(100, 150).len(), 3);
+        assert!(elo.ratings_for_window(200, 900).is_empty());
+        assert_eq!(elo["a"].number_of_games(), 3);
+    }
+
+    #[test]
+    fn has_plateaued() {
+        let mut elo = EloBuilder::new(HashMap::new()).history(true).build();
+        for i in 0..10 {
+            if i % 2 == 0 {
+                elo.add_game("a", "b", false).unwrap();
+            } else {
+                elo.add_game("b", "a", false).unwrap();
+            }
+        }
+        assert_eq!(elo.has_plateaued("a", 6, 40), Some(true));
+        assert_eq!(elo.has_plateaued("a", 6, 5), Some(false));
+
+        // a climbing player has not plateaued, even with a wide tolerance
+        for i in 0..6 {
+            elo.add_game("c", &format!("opponent{}", i), false).unwrap();
+        }
+        assert_eq!(elo.has_plateaued("c", 6, 1000), Some(false));
+        assert_eq!(elo.has_plateaued("d", 6, 40), None);
+
+        // the tolerance is in points, not stored units
+        let mut elo = EloBuilder::new(HashMap::new())
+            .history(true)
+            .precision_multiplier(10)
+            .build();
+        for i in 0..10 {
+            if i % 2 == 0 {
+                elo.add_game("a", "b", false).unwrap();
+            } else {
+                elo.add_game("b", "a", false).unwrap();
+            }
+        }
+        assert_eq!(elo.has_plateaued("a", 6, 40), Some(true));
+        assert_eq!(elo.has_plateaued("a", 6, 5), Some(false));
+    }
+
+    #[test]
+    fn ema_rating() {
+        let mut elo = EloBuilder::new(HashMap::new())
+            .ema_alpha(0.25)
+            .history(true)
+            .build();
+        let mut previous = (elo.starting_rating() as f64, 1000.0);
+        for i in 0..12 {
+            // mostly wins, with a loss every third game
+            if i % 3 == 2 {
+                elo.add_game("b", "a", false).unwrap();
+            } else {
+                elo.add_game("a", "b", false).unwrap();
+            }
+
+            let (rating, ema) = (elo["a"].rating() as f64, elo["a"].ema_rating());
+            assert!((ema - previous.1).abs() < (rating - previous.0).abs());
+            previous = (rating, ema);
+        }
+
+        // it follows the rise of the rating, a few games behind
+        let ema = elo["a"].ema_rating();
+        assert!(ema > 1025.0 && ema < *elo.rating_history("a").iter().max().unwrap() as f64);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ema_alpha_invalid() {
+        EloBuilder::new(HashMap::new()).ema_alpha(f64::NAN);
+    }
+
+    #[test]
+    fn allow_draws() {
+        let mut elo = EloBuilder::new(HashMap::new()).allow_draws(false).build();
+        assert_eq!(elo.add_game("a", "b", true), Err(EloError::DrawsNotAllowed));
+        assert_eq!(
+            elo.add_team_game(&["a"], &["b"], true),
+            Err(EloError::DrawsNotAllowed)
+        );
+        assert_eq!(
+            elo.add_team_game_score(&["a"], &["b"], 2, 2),
+            Err(EloError::DrawsNotAllowed)
+        );
+        assert!(elo.get_player("a").is_none());
+
+        let games = [("a", "b", true), ("a", "b", false)]
+            .map(|(p1, p2, is_draw)| (p1.to_string(), p2.to_string(), is_draw));
+        assert_eq!(elo.validate_games(&games), [(0, EloError::DrawsNotAllowed)]);
 
         elo.add_game("a", "b", false).unwrap();
         elo.add_game("b", "a", false).unwrap();
-
+        assert_eq!(elo["a"].number_of_games(), 2);
         assert_eq!(elo["a"].rating(), 999);
-        assert_eq!(elo["b"].rating(), 1001);
+    }
 
-        assert_eq!(elo["a"].number_of_games(), 2);
-        assert_eq!(elo["b"].number_of_games(), 2);
+    #[test]
+    fn expected_series_games() {
+        let mut elo = Elo::new(HashMap::new());
+        for name in ["a", "b", "c"] {
+            elo.add_player(name).unwrap();
+        }
+        elo["c"].rating = 1800;
+
+        // an even best-of-7 lasts 93/16 games
+        let even = elo.expected_series_games("a", "b", 7).unwrap();
+        assert!((even - 93.0 / 16.0).abs() < 1e-9);
+        let lopsided = elo.expected_series_games("a", "c", 7).unwrap();
+        assert!(lopsided < even && lopsided >= 4.0);
+
+        assert_eq!(elo.expected_series_games("a", "b", 1), Some(1.0));
+        assert_eq!(elo.expected_series_games("a", "b", 0), None);
     }
 
     #[test]
-    fn dual_draw() {
+    fn to_ascii_table() {
         let mut elo = Elo::new(HashMap::new());
-        elo.add_player("a");
-        elo.add_player("b");
+        elo.add_game("a", "a very long player name", false).unwrap();
+        elo.add_player("c").unwrap();
 
-        elo.add_game("a", "b", true).unwrap();
+        let table = elo.to_ascii_table(2);
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], "| Rank | Name             | Rating | Games |");
+        assert_eq!(lines[3], "|    1 | a                |   1016 |     1 |");
+        assert_eq!(lines[4], "|    2 | c                |   1000 |     0 |");
+        assert!(lines.iter().all(|line| line.len() == lines[0].len()));
 
-        assert_eq!(elo["a"].rating(), 1000);
-        assert_eq!(elo["b"].rating(), 1000);
+        let table = elo.to_ascii_table(10);
+        assert!(table.contains("|    3 | a very long p... |    984 |     1 |"));
+
+        let empty = Elo::new(HashMap::new()).to_ascii_table(10);
+        assert_eq!(empty.lines().count(), 3);
+        assert!(empty.contains("Rank"));
+    }
+
+    #[test]
+    fn decay_returning_player() {
+        let mut elo = EloBuilder::new(HashMap::new()).period_decay(0.1).build();
+        for name in ["a", "b", "c", "d"] {
+            elo.add_player(name).unwrap();
+        }
+        elo["a"].rating = 1400;
+        elo["b"].rating = 1400;
+
+        // the mean is 1200, a month away pulls 10% of the way there
+        elo.decay_returning_player("a", 1, 100).unwrap();
+        assert_eq!(elo["a"].rating(), 1380);
+
+        // years away would pull most of the way, but the cap stops it
+        elo.decay_returning_player("b", 36, 100).unwrap();
+        assert_eq!(elo["b"].rating(), 1300);
+
+        assert_eq!(
+            elo.decay_returning_player("e", 1, 100),
+            Err(EloError::PlayerNotFound("e".to_string()))
+        );
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn rank_probabilities() {
+        use rand::SeedableRng;
+
+        let mut elo = Elo::new(HashMap::new());
+        for (name, rating) in [("a", 1300), ("b", 1200), ("c", 1000)] {
+            elo.add_player(name).unwrap();
+            elo[name].rating = rating;
+        }
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+
+        let top = elo.rank_probabilities("a", 2000, &mut rng);
+        assert_eq!(top.len(), 3);
+        assert!(top[0] > top[1] && top[1] > top[2]);
+        assert!((top.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let second = elo.rank_probabilities("b", 2000, &mut rng);
+        assert!(top[0] > second[0]);
+        assert!(elo.rank_probabilities("d", 10, &mut rng).is_empty());
     }
 
     #[test]
     fn ordering() {
         let mut elo = Elo::new(HashMap::new());
-        elo.add_player("a");
-        elo.add_player("b");
-        elo.add_player("c");
-        elo.add_player("d");
+        elo.add_player("a").unwrap();
+        elo.add_player("b").unwrap();
+        elo.add_player("c").unwrap();
+        elo.add_player("d").unwrap();
 
         elo.add_game("a", "b", false).unwrap();
         elo.add_game("a", "b", false).unwrap();
@@ -174,7 +5122,7 @@ mod tests {
         elo["d"].number_of_games = 2;
 
         let hm = elo.into_storage();
-        let mut players = hm.iter().map(|(_, v)| v).collect::<Vec<_>>();
+        let mut players = hm.values().collect::<Vec<_>>();
         players.sort();
         assert_eq!(players[0].name(), "a");
         assert_eq!(players[1].name(), "b");
@@ -0,0 +1,45 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EloError {
+    /// No player with the given name exists
+    PlayerNotFound(String),
+    /// A player was entered as both sides of a game
+    SelfPlay(String),
+    /// Adding another player would exceed the maximum number of players
+    PoolFull(usize),
+    /// A player could not be created from the given values
+    InvalidPlayer(String),
+    /// A team game was entered with a team without players
+    EmptyTeam,
+    /// Imported data could not be parsed
+    ParseError(String),
+    /// The pool is locked and does not accept games
+    PoolLocked,
+    /// The storage could not be read or written
+    Storage(String),
+    /// A draw was entered, but the `Elo` is configured without draws
+    DrawsNotAllowed,
+}
+
+impl fmt::Display for EloError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EloError::PlayerNotFound(name) => write!(f, "player {} not found", name),
+            EloError::SelfPlay(name) => write!(
+                f,
+                "{} can't play against themselves (you friendless loser)",
+                name
+            ),
+            EloError::PoolFull(max) => write!(f, "the pool is full ({} players)", max),
+            EloError::InvalidPlayer(reason) => write!(f, "invalid player: {}", reason),
+            EloError::EmptyTeam => write!(f, "a team needs at least one player"),
+            EloError::ParseError(reason) => write!(f, "could not parse: {}", reason),
+            EloError::PoolLocked => write!(f, "the pool is locked"),
+            EloError::Storage(reason) => write!(f, "storage error: {}", reason),
+            EloError::DrawsNotAllowed => write!(f, "games can't end in a draw"),
+        }
+    }
+}
+
+impl std::error::Error for EloError {}
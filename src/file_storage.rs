@@ -0,0 +1,199 @@
+use crate::{EloError, EloStorage, Player};
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// An `EloStorage` kept in a JSON file. Changes are written with `save`, or automatically
+/// after every `save_every` changes.
+#[derive(Debug)]
+pub struct FileStorage {
+    path: PathBuf,
+    players: HashMap<String, Player>,
+    save_every: usize,
+    dirty: usize,
+    save_error: Option<EloError>,
+}
+
+impl FileStorage {
+    /// Load the players from `path`, or start empty if the file does not exist yet.
+    #[allow(dead_code)]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EloError> {
+        let path = path.as_ref().to_path_buf();
+        let players = if path.exists() {
+            let json = fs::read_to_string(&path).map_err(|e| EloError::Storage(e.to_string()))?;
+            serde_json::from_str::<Vec<Player>>(&json)
+                .map_err(|e| EloError::ParseError(e.to_string()))?
+                .into_iter()
+                .map(|player| (player.name().to_string(), player))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(FileStorage {
+            path,
+            players,
+            save_every: 0,
+            dirty: 0,
+            save_error: None,
+        })
+    }
+
+    /// Save automatically once `n` changes have been made since the last save, 0 to only save
+    /// on `save`. Every added, updated, removed or mutably borrowed player counts as a change.
+    /// As a change through a mutable borrow is made after the borrow, it is written on the next
+    /// change or when the storage is dropped. Failed automatic saves are kept for
+    /// `take_save_error`, except when saving on drop, where the error is lost.
+    #[allow(dead_code)]
+    pub fn save_every(mut self, n: usize) -> Self {
+        self.save_every = n;
+        self
+    }
+
+    /// Write all players to the file.
+    #[allow(dead_code)]
+    pub fn save(&mut self) -> Result<(), EloError> {
+        let mut players = self.players.values().collect::<Vec<_>>();
+        players.sort_by(|a, b| a.name().cmp(b.name()));
+        let json =
+            serde_json::to_string(&players).map_err(|e| EloError::ParseError(e.to_string()))?;
+        fs::write(&self.path, json).map_err(|e| EloError::Storage(e.to_string()))?;
+
+        self.dirty = 0;
+        Ok(())
+    }
+
+    /// The error of the last failed automatic save, if any, clearing it.
+    #[allow(dead_code)]
+    pub fn take_save_error(&mut self) -> Option<EloError> {
+        self.save_error.take()
+    }
+
+    fn save_due(&self) -> bool {
+        self.save_every > 0 && self.dirty >= self.save_every
+    }
+
+    /// Save if enough changes have been made, keeping the error if it fails.
+    fn save_if_due(&mut self) {
+        if self.save_due() {
+            if let Err(e) = self.save() {
+                self.save_error = Some(e);
+            }
+        }
+    }
+
+    /// Count a change that has been made, saving if it is due.
+    fn changed(&mut self) {
+        self.dirty += 1;
+        self.save_if_due();
+    }
+}
+
+impl EloStorage for FileStorage {
+    fn add_player(&mut self, player: Player) {
+        self.players.add_player(player);
+        self.changed();
+    }
+
+    fn update_player(&mut self, player: &Player) {
+        self.players.update_player(player);
+        self.changed();
+    }
+
+    fn get(&self, name: &str) -> Option<&Player> {
+        self.players.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Player> {
+        // the change through the borrow is only made after it, so it is saved with the next one
+        self.save_if_due();
+        self.dirty += 1;
+        self.players.get_mut(name)
+    }
+
+    fn all_players(&self) -> Vec<&Player> {
+        self.players.all_players()
+    }
+
+    fn remove_player(&mut self, name: &str) -> Option<Player> {
+        let player = self.players.remove_player(name);
+        self.changed();
+        player
+    }
+}
+
+/// Saves the changes that are due. An error here is lost, so call `save` or `take_save_error`
+/// before dropping to find out about failed saves.
+impl Drop for FileStorage {
+    fn drop(&mut self) {
+        if self.save_due() {
+            let _ = self.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Elo;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("elo-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn save() {
+        let path = temp_path("save");
+        let mut elo = Elo::new(FileStorage::open(&path).unwrap());
+        elo.add_game("a", "b", false).unwrap();
+        elo.storage_mut().save().unwrap();
+        drop(elo);
+
+        let elo = Elo::new(FileStorage::open(&path).unwrap());
+        assert_eq!(elo["a"].rating(), 1016);
+        assert_eq!(elo["b"].number_of_games(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn save_every() {
+        let path = temp_path("save-every");
+        let mut elo = Elo::new(FileStorage::open(&path).unwrap().save_every(1));
+        elo.add_game("a", "b", false).unwrap();
+        drop(elo);
+
+        let elo = Elo::new(FileStorage::open(&path).unwrap());
+        assert_eq!(elo["a"].rating(), 1016);
+        assert_eq!(elo["a"].number_of_games(), 1);
+        fs::remove_file(&path).unwrap();
+
+        // without auto-saving nothing is written
+        let mut elo = Elo::new(FileStorage::open(&path).unwrap());
+        elo.add_game("a", "b", false).unwrap();
+        drop(elo);
+        assert!(!path.exists());
+
+        // the nth change is written right away
+        let mut storage = FileStorage::open(&path).unwrap().save_every(2);
+        storage.add_player(Player::new("a".to_string(), 1000, 0));
+        assert!(!path.exists());
+        storage.add_player(Player::new("b".to_string(), 1000, 0));
+        assert_eq!(FileStorage::open(&path).unwrap().all_players().len(), 2);
+        fs::remove_file(&path).unwrap();
+
+        // failed automatic saves are kept until taken
+        let path = temp_path("missing").join("players.json");
+        let mut storage = FileStorage::open(&path).unwrap().save_every(1);
+        storage.add_player(Player::new("a".to_string(), 1000, 0));
+        assert!(matches!(
+            storage.take_save_error(),
+            Some(EloError::Storage(_))
+        ));
+        assert_eq!(storage.take_save_error(), None);
+    }
+}
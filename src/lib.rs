@@ -2,25 +2,77 @@
 mod async_elo;
 
 mod elo;
+mod error;
+#[cfg(feature = "serde")]
+mod file_storage;
+mod shared_elo;
 
-pub use crate::elo::{Elo, EloStorage};
+pub use crate::elo::{
+    diff, optimal_k, ranking_correlation, sensitivity, AuditEntry, Elo, EloBuilder, EloConfig,
+    EloSnapshot, EloStorage, GameRecord, PlayerEntry, UpsetRecord, VacantEntry,
+};
+
+pub use crate::error::EloError;
+#[cfg(feature = "serde")]
+pub use crate::file_storage::FileStorage;
+pub use crate::shared_elo::SharedElo;
 
 #[cfg(feature = "async")]
 pub use async_elo::{AsyncElo, AsyncEloStorage};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+use std::collections::{HashSet, VecDeque};
+
+/// The result of a game from the point of view of one player
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player {
     name: String,
+    display_name: String,
     rating: usize,
     number_of_games: usize,
+    recent_results: VecDeque<GameResult>,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    last_played: Option<u64>,
+    games_today: usize,
+    milestones: Vec<usize>,
+    labels: HashSet<String>,
+    deviation: Option<usize>,
+    win_streak: u32,
+    longest_win_streak: u32,
+    k_multiplier: f64,
+    ema_rating: f64,
 }
 
 impl Player {
     pub fn new(name: String, rating: usize, number_of_games: usize) -> Self {
         Self {
+            display_name: name.clone(),
             name,
             rating,
             number_of_games,
+            recent_results: VecDeque::new(),
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            last_played: None,
+            games_today: 0,
+            milestones: Vec::new(),
+            labels: HashSet::new(),
+            deviation: None,
+            win_streak: 0,
+            longest_win_streak: 0,
+            k_multiplier: 1.0,
+            ema_rating: rating as f64,
         }
     }
 
@@ -28,50 +80,462 @@ impl Player {
         self.rating
     }
 
+    /// Like `new`, but rejects names that are empty or only whitespace.
+    pub fn try_new<TS: ToString>(
+        name: TS,
+        rating: usize,
+        number_of_games: usize,
+    ) -> Result<Self, EloError> {
+        let name = name.to_string();
+        if name.trim().is_empty() {
+            return Err(EloError::InvalidPlayer("name is empty".to_string()));
+        }
+
+        Ok(Self::new(name, rating, number_of_games))
+    }
+
+    /// The name used as the storage key
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// The name as it was first entered, which may differ from the key in case
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
     pub fn number_of_games(&self) -> usize {
         self.number_of_games
     }
+
+    /// The results of the last few games, newest last.
+    /// Only kept if the `Elo` is configured with a recent results window.
+    pub fn recent_results(&self) -> Vec<GameResult> {
+        self.recent_results.iter().copied().collect()
+    }
+
+    /// Number of games won, only counting games recorded with a result
+    pub fn wins(&self) -> usize {
+        self.wins
+    }
+
+    pub fn losses(&self) -> usize {
+        self.losses
+    }
+
+    pub fn draws(&self) -> usize {
+        self.draws
+    }
+
+    /// When the player last played a game, in seconds since the unix epoch
+    pub fn last_played(&self) -> Option<u64> {
+        self.last_played
+    }
+
+    /// The rating milestones the player has reached, in the order they were reached
+    pub fn milestones(&self) -> &[usize] {
+        &self.milestones
+    }
+
+    /// How uncertain the rating is, as a Glicko style rating deviation in points, if tracked
+    pub fn deviation(&self) -> Option<usize> {
+        self.deviation
+    }
+
+    pub fn set_deviation(&mut self, deviation: Option<usize>) {
+        self.deviation = deviation;
+    }
+
+    /// The number of games won in a row, up to the last game
+    pub fn win_streak(&self) -> u32 {
+        self.win_streak
+    }
+
+    /// The most games the player has ever won in a row
+    pub fn longest_win_streak(&self) -> u32 {
+        self.longest_win_streak
+    }
+
+    /// An exponential moving average of the rating after each game, which moves less than the
+    /// rating itself
+    pub fn ema_rating(&self) -> f64 {
+        self.ema_rating
+    }
+
+    /// How much more (or less) the rating of the player moves than the K-factor says, 1 by default
+    pub fn k_multiplier(&self) -> f64 {
+        self.k_multiplier
+    }
+
+    /// Tag the player with a label, like their region or club
+    pub fn add_label<TS: ToString>(&mut self, label: TS) {
+        self.labels.insert(label.to_string());
+    }
+
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.contains(label)
+    }
+
+    pub fn labels(&self) -> &HashSet<String> {
+        &self.labels
+    }
+
+    /// Count a played game, keeping the last `window` results
+    fn record_result(&mut self, result: GameResult, window: usize) {
+        self.number_of_games += 1;
+        match result {
+            GameResult::Win => self.wins += 1,
+            GameResult::Loss => self.losses += 1,
+            GameResult::Draw => self.draws += 1,
+        }
+
+        if result == GameResult::Win {
+            self.win_streak += 1;
+            self.longest_win_streak = self.longest_win_streak.max(self.win_streak);
+        } else {
+            self.win_streak = 0;
+        }
+
+        if window > 0 {
+            self.recent_results.push_back(result);
+            while self.recent_results.len() > window {
+                self.recent_results.pop_front();
+            }
+        }
+    }
 }
 
+// the k multiplier and ema rating are never NaN, as both are checked when they are set
+impl Eq for Player {}
+
 impl PartialOrd for Player {
     fn partial_cmp(&self, other: &Player) -> Option<std::cmp::Ordering> {
-        Some(match self.rating.cmp(&other.rating).reverse() {
-            std::cmp::Ordering::Equal => {
-                match self.number_of_games.cmp(&other.number_of_games).reverse() {
-                    std::cmp::Ordering::Equal => self.name.cmp(&other.name),
-                    ord => ord,
-                }
+        Some(self.cmp(other))
+    }
+}
+
+/// The rating difference at which the stronger player is expected to score 10 times as much
+const SCALE: f64 = 400.0;
+
+/// How the expected score is computed from the rating difference
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Model {
+    /// The logistic curve of standard Elo
+    #[default]
+    Logistic,
+    /// Thurstone-Mosteller, where performances are normally distributed
+    Thurstone,
+}
+
+impl Model {
+    /// The expected score of a player rated `gap` points above their opponent
+    pub fn expected_score(self, gap: f64) -> f64 {
+        match self {
+            Model::Logistic => expected_score_for_gap(gap),
+            // every player performs with a standard deviation of half the scale
+            Model::Thurstone => normal_cdf(gap / (SCALE / 2.0 * std::f64::consts::SQRT_2)),
+        }
+    }
+
+    /// How many points higher a player must be rated to have an expected score of `prob`, the
+    /// inverse of `expected_score`.
+    ///
+    /// # Panics
+    /// If `prob` is not strictly between 0 and 1.
+    pub fn gap_for_probability(self, prob: f64) -> f64 {
+        match self {
+            Model::Logistic => rating_gap_for_probability(prob),
+            Model::Thurstone => {
+                assert!(prob > 0.0 && prob < 1.0, "probability must be in (0, 1)");
+                normal_quantile(prob) * SCALE / 2.0 * std::f64::consts::SQRT_2
             }
-            ord => ord,
-        })
+        }
     }
 }
 
-/// finds new ratings for two players
+/// The default maximum rating change from a single game
+const K_FACTOR: f64 = 32.0;
+
+/// The expected score of a player with `rating` against a player with `opponent_rating`
 /// https://en.wikipedia.org/wiki/Elo_rating_system#Mathematical_details
-pub fn update_rating(w: &Player, l: &Player, is_draw: bool) -> (usize, usize) {
-    let winner_expected =
-        1.0 / (1.0 + 10.0_f64.powf((l.rating as isize - w.rating as isize) as f64 / 400.0));
-    let loser_expected =
-        1.0 / (1.0 + 10.0_f64.powf((w.rating as isize - l.rating as isize) as f64 / 400.0));
+pub fn expected_score(rating: usize, opponent_rating: usize) -> f64 {
+    expected_score_for_gap(rating as f64 - opponent_rating as f64)
+}
+
+/// The expected score of a player rated `gap` points above their opponent
+pub(crate) fn expected_score_for_gap(gap: f64) -> f64 {
+    1.0 / (1.0 + 10.0_f64.powf(-gap / SCALE))
+}
+
+/// How many points higher a player must be rated to have an expected score of `prob`,
+/// the inverse of `expected_score`.
+///
+/// # Panics
+/// If `prob` is not strictly between 0 and 1.
+pub fn rating_gap_for_probability(prob: f64) -> f64 {
+    assert!(
+        prob > 0.0 && prob < 1.0,
+        "probability must be in (0, 1), got {}",
+        prob
+    );
+    SCALE * (prob / (1.0 - prob)).log10()
+}
+
+/// The unrounded rating changes for the winner and loser of a game
+pub(crate) fn rating_changes(w: usize, l: usize, is_draw: bool, k_factor: f64) -> (f64, f64) {
+    let winner_expected = expected_score(w, l);
+    let loser_expected = expected_score(l, w);
 
     let factor = if is_draw { 0.5 } else { 0.0 };
 
-    let winner_new_rating = w.rating() as f64 + 32.0 * (1.0 - factor - winner_expected);
-    let loser_new_rating = l.rating() as f64 + 32.0 * (factor - loser_expected);
+    (
+        k_factor * (1.0 - factor - winner_expected),
+        k_factor * (factor - loser_expected),
+    )
+}
+
+/// The rating at which a player who truly scores `win_prob` against players rated `pool_mean`
+/// would settle, where their expected score matches their actual results.
+///
+/// # Panics
+/// If `win_prob` is not strictly between 0 and 1.
+pub fn equilibrium_rating(pool_mean: usize, win_prob: f64) -> usize {
+    (pool_mean as f64 + rating_gap_for_probability(win_prob))
+        .round()
+        .max(0.0) as usize
+}
+
+/// Estimate the rating of an unrated player from their record against players rated around
+/// `pool_mean`. Half a win and half a loss are added to the record, so unbeaten (or winless)
+/// players get a finite estimate and a player without games is estimated at the pool mean.
+pub fn estimate_rating_from_record(wins: u32, losses: u32, pool_mean: usize) -> usize {
+    let win_ratio = (wins as f64 + 0.5) / (wins as f64 + losses as f64 + 1.0);
+    equilibrium_rating(pool_mean, win_ratio)
+}
+
+/// The probability that a standard normal variable is below `x`
+pub(crate) fn normal_cdf(x: f64) -> f64 {
+    // Numerical Recipes' erfc approximation, with a fractional error below 1.2e-7
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let erfc = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+
+    if x >= 0.0 {
+        1.0 - erfc / 2.0
+    } else {
+        erfc / 2.0
+    }
+}
+
+/// The z-score below which a standard normal variable falls with probability `p`
+/// (Acklam's rational approximation)
+pub(crate) fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.383_577_518_672_69e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// finds new ratings for two players
+/// https://en.wikipedia.org/wiki/Elo_rating_system#Mathematical_details
+pub fn update_rating(w: &Player, l: &Player, is_draw: bool) -> (usize, usize) {
+    let (winner_change, loser_change) = rating_changes(w.rating, l.rating, is_draw, K_FACTOR);
 
     (
-        winner_new_rating.round() as usize,
-        loser_new_rating.round() as usize,
+        (w.rating() as f64 + winner_change).round() as usize,
+        (l.rating() as f64 + loser_change).round() as usize,
     )
 }
 
+/// How to order players with the same rating.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TieBreak {
+    /// The player with more games played ranks higher (the default `Ord`).
+    #[default]
+    MoreGamesFirst,
+    /// The player with fewer games played ranks higher.
+    FewerGamesFirst,
+}
+
+impl Player {
+    /// Compare players by rating (highest first), breaking ties by number of games as given by
+    /// `tie_break`, and then by name.
+    pub fn cmp_with(&self, other: &Player, tie_break: TieBreak) -> std::cmp::Ordering {
+        let games = self.number_of_games.cmp(&other.number_of_games);
+        let games = match tie_break {
+            TieBreak::MoreGamesFirst => games.reverse(),
+            TieBreak::FewerGamesFirst => games,
+        };
+
+        self.rating
+            .cmp(&other.rating)
+            .reverse()
+            .then(games)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
 impl Ord for Player {
     fn cmp(&self, other: &Player) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // the rest of the player only breaks ties, so `cmp` finds players equal exactly when `==` does
+        let labels = |p: &Player| {
+            let mut labels = p.labels.iter().cloned().collect::<Vec<_>>();
+            labels.sort();
+            labels
+        };
+
+        self.cmp_with(other, TieBreak::default())
+            .then_with(|| self.display_name.cmp(&other.display_name))
+            .then_with(|| {
+                (self.wins, self.losses, self.draws).cmp(&(other.wins, other.losses, other.draws))
+            })
+            .then_with(|| {
+                (self.last_played, self.games_today).cmp(&(other.last_played, other.games_today))
+            })
+            .then_with(|| self.milestones.cmp(&other.milestones))
+            .then_with(|| labels(self).cmp(&labels(other)))
+            .then_with(|| self.deviation.cmp(&other.deviation))
+            .then_with(|| {
+                (self.win_streak, self.longest_win_streak)
+                    .cmp(&(other.win_streak, other.longest_win_streak))
+            })
+            .then_with(|| self.recent_results.cmp(&other.recent_results))
+            .then_with(|| self.k_multiplier.total_cmp(&other.k_multiplier))
+            .then_with(|| self.ema_rating.total_cmp(&other.ema_rating))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rating_gap() {
+        assert!((rating_gap_for_probability(0.909) - 400.0).abs() < 1.0);
+        assert_eq!(rating_gap_for_probability(0.5), 0.0);
+        assert!(rating_gap_for_probability(0.25) < 0.0);
+    }
+
+    #[test]
+    fn equilibrium_rating() {
+        let rating = super::equilibrium_rating(1500, 0.75);
+        assert!(rating.abs_diff(1700) < 15);
+        assert_eq!(super::equilibrium_rating(1500, 0.5), 1500);
+        assert!(super::equilibrium_rating(1500, 0.25) < 1500);
+    }
+
+    #[test]
+    fn estimate_rating_from_record() {
+        assert!(super::estimate_rating_from_record(3, 1, 1500) > 1500);
+        assert!(super::estimate_rating_from_record(1, 3, 1500) < 1500);
+        assert_eq!(super::estimate_rating_from_record(2, 2, 1500), 1500);
+        assert_eq!(super::estimate_rating_from_record(0, 0, 1500), 1500);
+        assert!(super::estimate_rating_from_record(10, 0, 1500) > 1800);
+    }
+
+    #[test]
+    fn gap_for_probability() {
+        for model in [Model::Logistic, Model::Thurstone] {
+            let gap = model.gap_for_probability(0.8);
+            assert!((model.expected_score(gap) - 0.8).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn models() {
+        for model in [Model::Logistic, Model::Thurstone] {
+            assert!((model.expected_score(0.0) - 0.5).abs() < 1e-6);
+        }
+
+        let logistic = Model::Logistic.expected_score(400.0);
+        let thurstone = Model::Thurstone.expected_score(400.0);
+        assert!((logistic - thurstone).abs() > 0.001);
+        assert!((logistic - thurstone).abs() < 0.05);
+        assert!((normal_cdf(1.959964) - 0.975).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normal_quantile() {
+        assert!(super::normal_quantile(0.5).abs() < 1e-9);
+        assert!((super::normal_quantile(0.975) - 1.959964).abs() < 1e-5);
+        assert!((super::normal_quantile(0.01) + 2.326348).abs() < 1e-5);
+    }
+
+    #[test]
+    fn try_new() {
+        assert!(Player::try_new("", 1000, 0).is_err());
+        assert!(Player::try_new("  ", 1000, 0).is_err());
+
+        let player = Player::try_new("a", 1000, 0).unwrap();
+        assert_eq!(player, Player::new("a".to_string(), 1000, 0));
+    }
+
+    #[test]
+    fn ord_agrees_with_eq() {
+        let player = Player::new("a".to_string(), 1000, 0);
+        let mut labelled = player.clone();
+        labelled.add_label("club");
+
+        assert_eq!(player.cmp(&player.clone()), std::cmp::Ordering::Equal);
+        assert_ne!(player, labelled);
+        assert_ne!(player.cmp(&labelled), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rating_gap_invalid() {
+        rating_gap_for_probability(1.0);
     }
 }
@@ -0,0 +1,91 @@
+use crate::{Elo, EloError, Player};
+
+use std::collections::HashMap;
+
+use std::sync::Mutex;
+
+/// An `Elo` that can be shared between threads, locking internally on every call.
+#[derive(Debug)]
+pub struct SharedElo {
+    elo: Mutex<Elo<HashMap<String, Player>>>,
+}
+
+impl SharedElo {
+    #[allow(dead_code)]
+    pub fn new(elo: Elo<HashMap<String, Player>>) -> Self {
+        SharedElo {
+            elo: Mutex::new(elo),
+        }
+    }
+
+    /// If is_draw is true, the game is a draw.
+    /// If is_draw is false, the game is won by the first player.
+    #[allow(dead_code)]
+    pub fn add_game(&self, player1: &str, player2: &str, is_draw: bool) -> Result<(), EloError> {
+        self.elo.lock().unwrap().add_game(player1, player2, is_draw)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_player(&self, name: &str) -> Option<Player> {
+        self.elo.lock().unwrap().get_player(name).cloned()
+    }
+
+    /// All players, sorted with the highest rating first.
+    #[allow(dead_code)]
+    pub fn leaderboard(&self) -> Vec<Player> {
+        self.elo
+            .lock()
+            .unwrap()
+            .leaderboard()
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn into_inner(self) -> Elo<HashMap<String, Player>> {
+        self.elo.into_inner().unwrap()
+    }
+}
+
+impl Default for SharedElo {
+    fn default() -> Self {
+        SharedElo::new(Elo::new(HashMap::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn threads() {
+        let elo = Arc::new(SharedElo::default());
+
+        let handles = (0..4)
+            .map(|i| {
+                let elo = Arc::clone(&elo);
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        elo.add_game("a", "b", false).unwrap();
+                        elo.add_game(&format!("t{}", i), "a", true).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(elo.get_player("a").unwrap().number_of_games(), 80);
+        assert_eq!(elo.get_player("b").unwrap().number_of_games(), 40);
+        assert_eq!(elo.get_player("t0").unwrap().number_of_games(), 10);
+
+        let leaderboard = elo.leaderboard();
+        assert_eq!(leaderboard.len(), 6);
+        assert_eq!(leaderboard[0].name(), "a");
+    }
+}